@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+///Fold every thread's piece of `data` into a partial of its own, then merge the partials in a tree
+///
+/// Threads never share a partial, so nothing is locked while the data is being folded
+fn aggregate<C, T, P, A, M>(thread_count: C, data: Vec<T>, accumulate: A, merge: M) -> P
+    where C: ThreadCount, T: Send + Sync + 'static, P: Default + Send + 'static,
+          A: Fn(&mut P, &T) + Send + Sync + 'static, M: Fn(P, P) -> P + Send + Sync + 'static
+{
+    let threads = threads_for(thread_count, data.len());
+
+    let lake = Builder::with_data(threads, (data, accumulate, merge))
+        .spawn(|x: ThreadUtilities<(Vec<T>, A, M)>| {
+            let (data, accumulate, merge) = x.data();
+
+            let mut partial = P::default();
+            for element in x.split_slice(data) {
+                accumulate(&mut partial, element);
+            }
+
+            x.reduce(partial, merge)
+        });
+
+    lake.join_all().into_iter().flatten().next().unwrap_or_default()
+}
+
+///Count how many elements of `data` fall into each of `buckets` buckets, with `bucket` giving the bucket of an element
+///
+/// Each thread counts into a dense `Vec` of its own. Panics if `bucket` returns a bucket out of range
+pub fn lake_histogram<C, T, B>(thread_count: C, data: Vec<T>, bucket: B, buckets: usize) -> Vec<usize>
+    where C: ThreadCount, T: Send + Sync + 'static, B: Fn(&T) -> usize + Send + Sync + 'static
+{
+    let counts = aggregate(thread_count, data, move |counts: &mut Vec<usize>, element| {
+        if counts.is_empty() {
+            counts.resize(buckets, 0);
+        }
+
+        let bucket = bucket(element);
+        assert!(bucket < buckets, "bucket {} is out of range for a histogram with {} buckets", bucket, buckets);
+        counts[bucket] += 1;
+    }, merge_counts);
+
+    //Threads that were given no elements never sized their counts
+    if counts.is_empty() { vec![0; buckets] } else { counts }
+}
+
+fn merge_counts(mut left: Vec<usize>, right: Vec<usize>) -> Vec<usize> {
+    if left.len() < right.len() {
+        return merge_counts(right, left)
+    }
+
+    for (count, other) in left.iter_mut().zip(right) {
+        *count += other;
+    }
+
+    left
+}
+
+///Count how many elements of `data` fall into each bucket, for buckets that are too many or too spread out for [`lake_histogram`]
+///
+/// Each thread counts into a `HashMap` of its own, holding only the buckets it has seen
+pub fn lake_histogram_sparse<C, T, K, B>(thread_count: C, data: Vec<T>, bucket: B) -> HashMap<K, usize>
+    where C: ThreadCount, T: Send + Sync + 'static, K: Hash + Eq + Send + 'static, B: Fn(&T) -> K + Send + Sync + 'static
+{
+    aggregate(thread_count, data, move |counts: &mut HashMap<K, usize>, element| {
+        *counts.entry(bucket(element)).or_insert(0) += 1;
+    }, |mut left, right| {
+        for (key, count) in right {
+            *left.entry(key).or_insert(0) += count;
+        }
+
+        left
+    })
+}
+
+///Group the elements of `data` by key, folding the values in each group into one
+///
+/// `key` gives the key and value for an element, and `fold` combines two values with the same key. Values are folded
+/// in the order of the elements they came from, so `fold` needs to be associative but not commutative.
+pub fn lake_group_by<C, T, K, V, G, F>(thread_count: C, data: Vec<T>, key: G, fold: F) -> HashMap<K, V>
+    where C: ThreadCount, T: Send + Sync + 'static, K: Hash + Eq + Send + 'static, V: Send + 'static,
+          G: Fn(&T) -> (K, V) + Send + Sync + 'static, F: Fn(V, V) -> V + Send + Sync + 'static
+{
+    let fold = Arc::new(fold);
+    let merge = fold.clone();
+
+    aggregate(thread_count, data, move |groups: &mut HashMap<K, V>, element| {
+        let (key, value) = key(element);
+        insert(groups, key, value, &*fold);
+    }, move |mut left, right| {
+        for (key, value) in right {
+            insert(&mut left, key, value, &*merge);
+        }
+
+        left
+    })
+}
+
+///Fold `value` into the group for `key`, after any value already there
+fn insert<K: Hash + Eq, V, F: Fn(V, V) -> V>(groups: &mut HashMap<K, V>, key: K, value: V, fold: &F) {
+    let value = match groups.remove(&key) {
+        Some(previous) => fold(previous, value),
+        None => value,
+    };
+
+    groups.insert(key, value);
+}
@@ -0,0 +1,122 @@
+use std::any::Any;
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+struct State {
+    _parties: usize, //Threads whose closure is still running
+    _arrived: usize,
+    _generation: u64,
+    _poisoned: bool,
+    _leader: Option<u64>, //A generation opened by a thread leaving, whose leader is still to be picked from the waiters
+}
+
+///A barrier for the threads of a lake, which copes with threads finishing or panicking while others wait
+///
+/// A thread whose closure returns no longer takes part, so the others are not left waiting for it. A thread whose
+/// closure panics poisons the barrier, and every thread waiting on it panics too rather than waiting forever.
+pub (crate) struct Barrier {
+    _state: Mutex<State>,
+    _condvar: Condvar,
+    _slots: Mutex<Vec<Option<Box<dyn Any + Send>>>>, //A value from each thread, exchanged between two waits at the barrier
+}
+
+pub (crate) const POISONED: &str = "another thread in the lake panicked before reaching the barrier";
+
+impl Barrier {
+
+    pub (crate) fn new(threads: usize) -> Self {
+        Self {
+            _state: Mutex::new(State { _parties: threads, _arrived: 0, _generation: 0, _poisoned: false, _leader: None }),
+            _condvar: Condvar::new(),
+            _slots: Mutex::new((0..threads).map(|_| None).collect()),
+        }
+    }
+
+    ///Leave a value for the other threads to read once everyone has reached the barrier
+    pub (crate) fn put<V: Send + 'static>(&self, index: usize, value: V) {
+        self._slots.lock().unwrap()[index] = Some(Box::new(value));
+    }
+
+    ///Take the value left by one thread
+    pub (crate) fn take<V: 'static>(&self, index: usize) -> V {
+        let slot = self._slots.lock().unwrap()[index].take().expect("every thread in the lake must take part");
+        *slot.downcast().expect("every thread must exchange values of the same type")
+    }
+
+    ///Read the value left by every thread
+    pub (crate) fn gather<V: Clone + 'static>(&self) -> Vec<V> {
+        self._slots.lock().unwrap().iter().map(|slot| {
+            let slot = slot.as_ref().expect("every thread in the lake must take part");
+            slot.downcast_ref::<V>().expect("every thread must exchange values of the same type").clone()
+        }).collect()
+    }
+
+    ///Wait for every other thread taking part. Returns true for exactly one thread each time the barrier opens
+    pub (crate) fn wait(&self) -> bool {
+        let mut state = self._state.lock().unwrap();
+
+        if state._poisoned {
+            drop(state);
+            panic!("{}", POISONED)
+        }
+
+        state._arrived += 1;
+
+        if state._arrived == state._parties {
+            self.open(&mut state);
+            return true
+        }
+
+        let generation = state._generation;
+        let mut state = self._condvar.wait_while(state, |state| state._generation == generation && !state._poisoned).unwrap();
+
+        if state._generation == generation {
+            drop(state);
+            panic!("{}", POISONED)
+        }
+
+        //If a leaving thread opened the barrier, the first waiter to wake up leads in its place
+        if state._leader == Some(generation) {
+            state._leader = None;
+            return true
+        }
+
+        false
+    }
+
+    ///Called when a thread's closure returns, so the barrier stops waiting for it
+    pub (crate) fn leave(&self) {
+        let mut state = self._state.lock().unwrap();
+
+        state._parties -= 1;
+
+        if state._arrived > 0 && state._arrived == state._parties {
+            state._leader = Some(state._generation);
+            self.open(&mut state);
+        }
+    }
+
+    ///Called when a thread's closure panics, releasing every waiting thread
+    pub (crate) fn poison(&self) {
+        self._state.lock().unwrap()._poisoned = true;
+        self._condvar.notify_all();
+    }
+
+    fn open(&self, state: &mut MutexGuard<'_, State>) {
+        state._arrived = 0;
+        state._generation += 1;
+        self._condvar.notify_all();
+    }
+}
+
+///Takes a thread out of the barrier when its closure finishes, poisoning the barrier if the closure panicked
+pub (crate) struct Leaving<'a>(pub (crate) &'a Barrier);
+
+impl<'a> Drop for Leaving<'a> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.poison();
+        } else {
+            self.0.leave();
+        }
+    }
+}
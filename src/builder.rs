@@ -4,6 +4,16 @@ use crate::traits::ThreadName;
 use crate::threadlake::ThreadLake;
 use crate::threadutilities::ThreadUtilities;
 use std::time::Duration;
+use crate::channels::{Channels, Overflow};
+use crate::registry::Registry;
+use crate::watch::Watch;
+use crate::incumbent::{Incumbent, Objective};
+use crate::frontier::Frontier;
+use crate::rng::Seed;
+use crate::traits::Score;
+use crate::queue::Queue;
+use crate::router::Router;
+use std::sync::Arc;
 
 ///Build a thread lake object
 pub struct Builder<D> {
@@ -12,6 +22,8 @@ pub struct Builder<D> {
     _strings: Vec<String>,
     _data: D,
     _check_sleep: Duration, //Time, in ms, to sleep between calls to check for unpause
+    _channels: Channels,
+    _registry: Registry,
 
 }
 
@@ -36,9 +48,16 @@ impl<D: Sync + Send + 'static> Builder<D> {
             _strings: Vec::with_capacity(_thread_count),
             _data: data,
             _check_sleep: Duration::from_millis(10),
+            _channels: Channels::new(),
+            _registry: Registry::new(),
         }
     }
 
+    ///The number of threads the lake will spawn
+    pub fn thread_count(&self) -> usize {
+        self._thread_count
+    }
+
     ///Sets the names for each thread
     pub fn names<F>(mut self, names: F) -> Self
         where F: ThreadName + Copy
@@ -49,12 +68,100 @@ impl<D: Sync + Send + 'static> Builder<D> {
         self
     }
 
-    ///Sets the names for each thread
-    pub fn check_sleep<F>(mut self, duration: Duration) -> Self {
+    ///Sets how long a paused thread sleeps between checks for the play or stop signal
+    pub fn check_sleep(mut self, duration: Duration) -> Self {
         self._check_sleep = duration;
         self
     }
 
+    ///Sets the seed the random number streams from [`ThreadUtilities::rng`] are derived from. The default seed is zero
+    pub fn seed(mut self, seed: u64) -> Self {
+        self._registry.insert(Seed(seed));
+        self
+    }
+
+    ///Declare an unbounded typed channel for messages of type `T`
+    ///
+    /// Threads send on it with [`ThreadUtilities::post`], and the lake receives with [`ThreadLake::channel`] or [`ThreadLake::select`]
+    pub fn channel<T: Send + 'static>(mut self) -> Self {
+        self._channels.declare::<T>(None, Overflow::Block);
+        self
+    }
+
+    ///Declare a typed channel for messages of type `T` that holds at most `capacity` messages, with `overflow` deciding what happens when it is full
+    pub fn bounded_channel<T: Send + 'static>(mut self, capacity: usize, overflow: Overflow) -> Self {
+        assert!(capacity > 0, "a bounded channel needs room for at least one message");
+        self._channels.declare::<T>(Some(capacity), overflow);
+        self
+    }
+
+    ///Declare a watch slot for values of type `W`, starting with `initial`
+    ///
+    /// The lake replaces the value with [`ThreadLake::publish`], and threads read the latest version with [`ThreadUtilities::current`]
+    pub fn watch<W: Send + Sync + 'static>(mut self, initial: W) -> Self {
+        self._registry.insert(Watch::new(initial));
+        self
+    }
+
+    ///Declare an incumbent holding the best solution, of type `V` with score `S`, found so far by any thread
+    ///
+    /// Threads get it with [`ThreadUtilities::incumbent`], and the lake with [`ThreadLake::incumbent`]
+    pub fn incumbent<S: Score, V: Send + 'static>(mut self, objective: Objective) -> Self {
+        self._registry.insert(Incumbent::<S, V>::new(objective));
+        self
+    }
+
+    ///Declare a frontier of items of type `T`, starting with `start`, and a visited set over `0..vertices`
+    ///
+    /// Threads get it with [`ThreadUtilities::frontier`], and the lake with [`ThreadLake::frontier`]
+    pub fn frontier<T: Send + Sync + 'static>(mut self, start: Vec<T>, vertices: usize) -> Self {
+        self._registry.insert(Frontier::new(start, vertices));
+        self
+    }
+
+    ///Declare an unbounded work queue for items of type `T`
+    ///
+    /// The lake feeds it with [`ThreadLake::submit`], and threads take items with [`ThreadUtilities::next_item`]
+    pub fn queue<T: Send + 'static>(mut self) -> Self {
+        self._registry.insert_queue::<T>(None);
+        self
+    }
+
+    ///Declare a work queue for items of type `T` that holds at most `capacity` items, making [`ThreadLake::submit`] block while it is full
+    pub fn bounded_queue<T: Send + 'static>(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "a bounded queue needs room for at least one item");
+        self._registry.insert_queue::<T>(Some(capacity));
+        self
+    }
+
+    ///Declare a router for messages of type `T`, giving every thread its own inbox
+    ///
+    /// Messages sent with [`ThreadLake::route`] or [`ThreadUtilities::route`] go to the thread that owns their key, which
+    /// takes them with [`ThreadUtilities::next_routed`]. This lets each thread own a shard of some state, such as a map,
+    /// and update it without locks.
+    pub fn router<T: Send + 'static>(mut self) -> Self {
+        self._registry.insert_router(Arc::new(Router::<T>::new(self._thread_count)));
+        self
+    }
+
+    ///Swap the data for `data`, keeping everything else set on the builder
+    pub (crate) fn replace_data<E: Sync + Send + 'static>(self, data: E) -> Builder<E> {
+        Builder {
+            _thread_count: self._thread_count,
+            _strings: self._strings,
+            _data: data,
+            _check_sleep: self._check_sleep,
+            _channels: self._channels,
+            _registry: self._registry,
+        }
+    }
+
+    ///Use an existing queue as the work queue for `T`
+    pub (crate) fn shared_queue<T: Send + 'static>(mut self, queue: Arc<Queue<T>>) -> Self {
+        self._registry.insert_shared_queue(queue);
+        self
+    }
+
     ///Consume the builder, spawn the threads, and return the thread lake object
     pub fn spawn<R, M, F>(self, f: F) -> ThreadLake<D, R, M>
         where M: Send + 'static, R: Send + 'static, F: Fn(ThreadUtilities<D, M>) -> R + Send + 'static + Clone +  Sync
@@ -66,7 +173,7 @@ impl<D: Sync + Send + 'static> Builder<D> {
             self
         };
 
-        let mut lake = ThreadLake::with_data(me._thread_count, me._data, me._strings);
+        let mut lake = ThreadLake::with_data(me._thread_count, me._data, me._strings, me._channels, me._registry, me._check_sleep);
 
         lake.spawn(f);
 
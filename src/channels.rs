@@ -0,0 +1,203 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{RecvError, TryRecvError, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use crate::queue::{Queue, Activity, Close, Pop};
+
+///Decides what a bounded channel does with a message sent while it is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    ///Block the sending thread until there is room
+    Block,
+    ///Discard the message being sent
+    DropNewest,
+    ///Discard the oldest message waiting in the channel to make room
+    DropOldest,
+}
+
+///A declared channel, once as its concrete queue type and once as something that can be closed
+type Entry = (Arc<dyn Any + Send + Sync>, Arc<dyn Close>);
+
+///The set of typed channels declared on a [`crate::builder::Builder`], keyed by message type
+pub (crate) struct Channels {
+    _queues: HashMap<TypeId, Entry>,
+    _activity: Arc<Activity>,
+}
+
+impl Channels {
+
+    pub (crate) fn new() -> Self {
+        Self { _queues: HashMap::new(), _activity: Arc::new(Activity::new()) }
+    }
+
+    ///Declare the channel for `T`, replacing any previous declaration
+    pub (crate) fn declare<T: Send + 'static>(&mut self, capacity: Option<usize>, overflow: Overflow) {
+        let queue = Arc::new(Queue::<T>::new(capacity, overflow, Some(self._activity.clone())));
+        self._queues.insert(TypeId::of::<T>(), (queue.clone(), queue));
+    }
+
+    pub (crate) fn get<T: Send + 'static>(&self) -> Option<&Queue<T>> {
+        self._queues.get(&TypeId::of::<T>()).and_then(|(queue, _)| queue.downcast_ref())
+    }
+
+    fn close(&self) {
+        for (_, queue) in self._queues.values() {
+            queue.close();
+        }
+    }
+}
+
+///The sending side of the channels, shared by all threads. When the last thread lets go, the channels are closed
+pub (crate) struct Senders(pub (crate) Arc<Channels>);
+
+impl Drop for Senders {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+///The receiving side of the channels, owned by the lake. When the lake lets go, the channels are closed
+pub (crate) struct Receivers(pub (crate) Arc<Channels>);
+
+impl Drop for Receivers {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+///The receiving end of one typed channel, obtained from [`crate::threadlake::ThreadLake::channel`]
+pub struct ChannelReceiver<'a, T> {
+    pub (crate) _queue: &'a Queue<T>,
+}
+
+impl<'a, T> ChannelReceiver<'a, T> {
+
+    ///Block until a message arrives. Fails once every thread has finished and the channel is empty
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self._queue.pop().ok_or(RecvError)
+    }
+
+    ///Take a message if one is waiting
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self._queue.try_pop() {
+            Pop::Item(item) => Ok(item),
+            Pop::Empty => Err(TryRecvError::Empty),
+            Pop::Closed => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    ///Block until a message arrives, or the timeout passes
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match self._queue.pop_timeout(timeout) {
+            Pop::Item(item) => Ok(item),
+            Pop::Empty => Err(RecvTimeoutError::Timeout),
+            Pop::Closed => Err(RecvTimeoutError::Disconnected),
+        }
+    }
+
+    ///Number of messages discarded because the channel was full
+    pub fn dropped(&self) -> usize {
+        self._queue.dropped()
+    }
+}
+
+enum Poll {
+    Ready,
+    Empty,
+    Closed,
+}
+
+///Waits on several typed channels at once, handing each message to the handler registered for its type
+///
+/// Created with [`crate::threadlake::ThreadLake::select`]. Each call to [`Select::wait`] handles exactly one message,
+/// taking turns between the channels so a busy channel cannot starve the others.
+pub struct Select<'a> {
+    _arms: Vec<Box<dyn FnMut() -> Poll + 'a>>,
+    _channels: &'a Channels,
+    _next: usize,
+}
+
+impl<'a> Select<'a> {
+
+    pub (crate) fn new(channels: &'a Channels) -> Self {
+        Self { _arms: Vec::new(), _channels: channels, _next: 0 }
+    }
+
+    ///Handle messages from the channel for `T` with `handler`
+    ///
+    /// A type with no declared channel is treated as a channel that is already closed
+    pub fn recv<T, F>(mut self, mut handler: F) -> Self
+        where T: Send + 'static, F: FnMut(T) + 'a
+    {
+        let queue = self._channels.get::<T>();
+
+        self._arms.push(Box::new(move || {
+            match queue.map_or(Pop::Closed, |queue| queue.try_pop()) {
+                Pop::Item(item) => {
+                    handler(item);
+                    Poll::Ready
+                }
+                Pop::Empty => Poll::Empty,
+                Pop::Closed => Poll::Closed,
+            }
+        }));
+
+        self
+    }
+
+    fn poll(&mut self) -> Poll {
+        let count = self._arms.len();
+        let mut closed = 0;
+
+        for offset in 0..count {
+            let arm = (self._next + offset) % count;
+
+            match (self._arms[arm])() {
+                Poll::Ready => {
+                    self._next = arm + 1;
+                    return Poll::Ready
+                }
+                Poll::Empty => {}
+                Poll::Closed => closed += 1,
+            }
+        }
+
+        if closed == count { Poll::Closed } else { Poll::Empty }
+    }
+
+    fn wait_deadline(&mut self, deadline: Option<Instant>) -> Result<(), RecvTimeoutError> {
+        loop {
+            let seen = self._channels._activity.current();
+
+            match self.poll() {
+                Poll::Ready => return Ok(()),
+                Poll::Closed => return Err(RecvTimeoutError::Disconnected),
+                Poll::Empty => {}
+            }
+
+            if !self._channels._activity.wait_past(seen, deadline) {
+                return Err(RecvTimeoutError::Timeout)
+            }
+        }
+    }
+
+    ///Block until one message has been handled. Fails once all of the selected channels are closed and empty
+    pub fn wait(&mut self) -> Result<(), RecvError> {
+        self.wait_deadline(None).map_err(|_| RecvError)
+    }
+
+    ///Handle one message if any of the selected channels has one waiting
+    pub fn try_wait(&mut self) -> Result<(), TryRecvError> {
+        match self.poll() {
+            Poll::Ready => Ok(()),
+            Poll::Empty => Err(TryRecvError::Empty),
+            Poll::Closed => Err(TryRecvError::Disconnected),
+        }
+    }
+
+    ///Block until one message has been handled, or the timeout passes
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<(), RecvTimeoutError> {
+        self.wait_deadline(Some(Instant::now() + timeout))
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, Float, threads_for};
+
+///Number of elements reduced together before the tree takes over. Fixed, so the order of operations never depends on the thread count
+const BLOCK: usize = 1024;
+
+///How [`lake_sum_deterministic`] adds up numbers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Summation {
+    ///Plain addition
+    Naive,
+    ///Neumaier's compensated summation, which tracks the rounding error of every addition and adds it back at the end
+    Compensated,
+}
+
+///Reduce each fixed-size block of `data` on a lake, returning the partial for every block in order
+///
+/// Blocks are shared out between the threads, but every block is reduced the same way whichever thread gets it
+fn blocks<C, T, P, F>(thread_count: C, data: Vec<T>, block: F) -> Vec<P>
+    where C: ThreadCount, T: Send + Sync + 'static, P: Send + 'static, F: Fn(&[T]) -> P + Send + Sync + 'static
+{
+    let threads = threads_for(thread_count, data.len());
+
+    let lake = Builder::with_data(threads, (data, block))
+        .spawn(|x: ThreadUtilities<(Vec<T>, F)>| {
+            let (data, block) = x.data();
+            let count = data.len().div_ceil(BLOCK);
+
+            x.range(count).map(|index| block(&data[index * BLOCK..((index + 1) * BLOCK).min(data.len())])).collect::<Vec<_>>()
+        });
+
+    lake.join_all().into_iter().flatten().collect()
+}
+
+///Combine neighbouring partials in pairs, level by level, until one is left
+fn tree<P, F: Fn(P, P) -> P>(mut partials: Vec<P>, op: F) -> Option<P> {
+    while partials.len() > 1 {
+        let mut pairs = partials.into_iter();
+        let mut next = Vec::with_capacity(pairs.len().div_ceil(2));
+
+        while let Some(left) = pairs.next() {
+            next.push(match pairs.next() {
+                Some(right) => op(left, right),
+                None => left,
+            });
+        }
+
+        partials = next;
+    }
+
+    partials.pop()
+}
+
+///A running sum, and the rounding error lost from it so far
+#[derive(Clone, Copy)]
+struct Sum<F> {
+    _sum: F,
+    _compensation: F,
+}
+
+impl<F: Float> Sum<F> {
+
+    fn add(self, value: F, summation: Summation) -> Self {
+        let sum = self._sum + value;
+
+        let compensation = match summation {
+            Summation::Naive => self._compensation,
+            Summation::Compensated if self._sum.abs() >= value.abs() => self._compensation + ((self._sum - sum) + value),
+            Summation::Compensated => self._compensation + ((value - sum) + self._sum),
+        };
+
+        Self { _sum: sum, _compensation: compensation }
+    }
+
+    fn merge(self, other: Self, summation: Summation) -> Self {
+        let sum = self.add(other._sum, summation);
+        Self { _sum: sum._sum, _compensation: sum._compensation + other._compensation }
+    }
+}
+
+///Add up `data` on a lake, getting exactly the same answer whatever the number of threads
+///
+/// The data is summed in blocks of a fixed size, and the block sums are added in pairs in a fixed tree, so the order
+/// of additions only depends on the length of the data.
+pub fn lake_sum_deterministic<C: ThreadCount, F: Float>(thread_count: C, data: Vec<F>, summation: Summation) -> F {
+    let zero = Sum { _sum: F::ZERO, _compensation: F::ZERO };
+
+    let partials = blocks(thread_count, data, move |block| block.iter().fold(zero, |sum, value| sum.add(*value, summation)));
+
+    let sum = tree(partials, |left, right| left.merge(right, summation)).unwrap_or(zero);
+
+    sum._sum + sum._compensation
+}
+
+///Reduce `data` with `op` on a lake, applying `op` in the same order whatever the number of threads
+///
+/// Like [`lake_sum_deterministic`], elements are reduced in order within fixed-size blocks, and the blocks in a fixed
+/// tree. Returns `None` if there is no data.
+pub fn lake_reduce_deterministic<C, T, F>(thread_count: C, data: Vec<T>, op: F) -> Option<T>
+    where C: ThreadCount, T: Clone + Send + Sync + 'static, F: Fn(&T, &T) -> T + Send + Sync + 'static
+{
+    let op = Arc::new(op);
+    let reduce = op.clone();
+
+    let partials = blocks(thread_count, data, move |block| {
+        block[1..].iter().fold(block[0].clone(), |partial, element| reduce(&partial, element))
+    });
+
+    tree(partials, |left, right| op(&left, &right))
+}
@@ -1,6 +1,9 @@
 use std::cell::UnsafeCell;
 use crate::threadutilities::ThreadUtilities;
 use crate::split::SubSliceMut;
+use crate::schedule::{Schedule, ScheduledPieces};
+use std::ops::Range;
+use std::mem::{ManuallyDrop, MaybeUninit};
 
 ///Disjointer takes a vector, and partitions it into roughly equal sized disjoint mutable slices
 ///
@@ -33,6 +36,55 @@ impl<T> Disjointer<T>
         }
     }
 
+    ///Like [`Disjointer::piece`], but split with [`ThreadUtilities::balanced_range`]
+    pub fn balanced_piece<D, M>(&self, utility: & ThreadUtilities<D, M>) -> SubSliceMut<'_, T> {
+        unsafe {
+            let entire_slice = (*self.0.get()).as_mut_slice();
+            utility.balanced_split_slice_mut(entire_slice)
+        }
+    }
+
+    ///Get the mutable subslices of the vector claimed by this thread from a dynamic or guided [`Schedule`]
+    ///
+    /// The schedule should have been created with the length of the vector.
+    ///
+    /// # Safety
+    ///
+    /// Schedules are matched up between threads by the order they are started in, so the slices only stay disjoint if
+    /// every thread passes this disjointer the schedule it started at the same point, and starts the same schedules
+    /// before it. No other schedule or piece of this disjointer may be in use at the same time.
+    pub unsafe fn pieces(&self, schedule: Schedule) -> ScheduledPieces<'_, T> {
+        assert!(schedule._total <= self.len(), "the schedule covers more elements than the vector holds");
+        ScheduledPieces { _schedule: schedule, _disjointer: self }
+    }
+
+    ///Number of elements in the vector
+    pub fn len(&self) -> usize {
+        unsafe { (*self.0.get()).len() }
+    }
+
+    ///Returns true if the vector is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Get a mutable slice of part of the vector, without forming a reference to the rest of it
+    ///
+    /// The caller must make sure no other thread can be given an overlapping slice at the same time
+    #[allow(clippy::mut_from_ref)]
+    pub (crate) unsafe fn slice_mut(&self, range: Range<usize>) -> &mut [T] {
+        assert!(range.start <= range.end && range.end <= self.len(), "range out of bounds for the vector");
+        std::slice::from_raw_parts_mut((*self.0.get()).as_mut_ptr().add(range.start), range.len())
+    }
+
+    ///Get a shared slice of part of the vector
+    ///
+    /// The caller must make sure no thread holds a mutable slice overlapping it at the same time
+    pub (crate) unsafe fn slice(&self, range: Range<usize>) -> &[T] {
+        assert!(range.start <= range.end && range.end <= self.len(), "range out of bounds for the vector");
+        std::slice::from_raw_parts((*self.0.get()).as_ptr().add(range.start), range.len())
+    }
+
     ///Unwrap the vector
     pub fn take(self) -> Vec<T> {
         self.0.into_inner()
@@ -40,3 +92,15 @@ impl<T> Disjointer<T>
 
 
 }
+
+///Reinterpret a vector as uninitialised, so a lake can move its elements around without them ever being dropped twice
+pub (crate) fn into_uninit<T>(vector: Vec<T>) -> Vec<MaybeUninit<T>> {
+    let mut vector = ManuallyDrop::new(vector);
+    unsafe { Vec::from_raw_parts(vector.as_mut_ptr() as *mut MaybeUninit<T>, vector.len(), vector.capacity()) }
+}
+
+///Reinterpret a vector of uninitialised elements as initialised. The caller must make sure every element has been initialised
+pub (crate) unsafe fn from_uninit<T>(vector: Vec<MaybeUninit<T>>) -> Vec<T> {
+    let mut vector = ManuallyDrop::new(vector);
+    Vec::from_raw_parts(vector.as_mut_ptr() as *mut T, vector.len(), vector.capacity())
+}
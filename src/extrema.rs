@@ -0,0 +1,140 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+///Find the indices of the `k` best elements of `data`, best first, where `order` puts better elements first
+///
+/// Each thread keeps its own `k` best candidates from its piece, in a heap of at most `k` entries, or as a single
+/// running best when `k` is one. The candidates from every thread are then ranked together. Elements that `order`
+/// ranks equally are ranked by index, lowest first, so the result never depends on which thread found what. The data
+/// is handed back along with the indices.
+fn best<C, T, F>(thread_count: C, data: Vec<T>, k: usize, order: F) -> (Vec<T>, Vec<usize>)
+    where C: ThreadCount, T: Send + Sync + 'static, F: Fn(&T, &T) -> Ordering + Send + Sync + 'static
+{
+    let threads = threads_for(thread_count, data.len());
+
+    let lake = Builder::with_data(threads, (data, order))
+        .spawn(move |x: ThreadUtilities<(Vec<T>, F)>| {
+            let (data, order) = x.data();
+            let rank = |a: &usize, b: &usize| order(&data[*a], &data[*b]).then(a.cmp(b));
+
+            let mut range = x.range(data.len());
+
+            match k {
+                0 => Vec::new(),
+                //A single running best needs no heap at all
+                1 => range.next().map(|first| range.fold(first, |best, index| {
+                    if rank(&index, &best) == Ordering::Less { index } else { best }
+                })).into_iter().collect(),
+                _ => {
+                    //The heap holds the k best candidates so far, with the worst of them on top, ready to be replaced
+                    let mut candidates = BinaryHeap::with_capacity(k.min(range.len()));
+
+                    for index in range {
+                        let candidate = Candidate { _index: index, _rank: &rank };
+
+                        if candidates.len() < k {
+                            candidates.push(candidate);
+                        } else if let Some(mut worst) = candidates.peek_mut() {
+                            if candidate < *worst {
+                                *worst = candidate;
+                            }
+                        }
+                    }
+
+                    candidates.into_iter().map(|candidate| candidate._index).collect()
+                }
+            }
+        });
+
+    let (candidates, (data, order)) = lake.join_with_data();
+    let mut candidates: Vec<usize> = candidates.into_iter().flatten().collect();
+
+    candidates.sort_by(|a, b| order(&data[*a], &data[*b]).then(a.cmp(b)));
+    candidates.truncate(k);
+
+    (data, candidates)
+}
+
+///An index into the data, ordered by how the element there ranks, so better candidates compare less
+struct Candidate<'a, R> {
+    _index: usize,
+    _rank: &'a R,
+}
+
+impl<'a, R: Fn(&usize, &usize) -> Ordering> Ord for Candidate<'a, R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self._rank)(&self._index, &other._index)
+    }
+}
+
+impl<'a, R: Fn(&usize, &usize) -> Ordering> PartialOrd for Candidate<'a, R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, R: Fn(&usize, &usize) -> Ordering> PartialEq for Candidate<'a, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a, R: Fn(&usize, &usize) -> Ordering> Eq for Candidate<'a, R> {}
+
+///Move the elements at `indices` out of `data`, in the order of `indices`
+fn take<T>(data: Vec<T>, indices: &[usize]) -> Vec<T> {
+    let mut wanted: Vec<(usize, usize)> = indices.iter().enumerate().map(|(position, index)| (*index, position)).collect();
+    wanted.sort_unstable();
+
+    let mut taken: Vec<Option<T>> = indices.iter().map(|_| None).collect();
+    let mut wanted = wanted.into_iter().peekable();
+
+    for (index, element) in data.into_iter().enumerate() {
+        if let Some((_, position)) = wanted.next_if(|(wanted, _)| *wanted == index) {
+            taken[position] = Some(element);
+        }
+    }
+
+    taken.into_iter().map(|element| element.unwrap()).collect()
+}
+
+///The `k` greatest elements of `data` according to `compare`, greatest first, found on a lake
+///
+/// Of elements that compare equal, those earlier in `data` come first
+pub fn lake_top_k<C, T, F>(thread_count: C, data: Vec<T>, k: usize, compare: F) -> Vec<T>
+    where C: ThreadCount, T: Send + Sync + 'static, F: Fn(&T, &T) -> Ordering + Send + Sync + 'static
+{
+    let (data, indices) = best(thread_count, data, k, move |a, b| compare(b, a));
+    take(data, &indices)
+}
+
+///The element of `data` with the smallest key, found on a lake. Of elements with equal keys, the first is returned
+pub fn lake_min_by_key<C, T, K, F>(thread_count: C, data: Vec<T>, key: F) -> Option<T>
+    where C: ThreadCount, T: Send + Sync + 'static, K: Ord, F: Fn(&T) -> K + Send + Sync + 'static
+{
+    let (data, indices) = best(thread_count, data, 1, move |a, b| key(a).cmp(&key(b)));
+    take(data, &indices).pop()
+}
+
+///The element of `data` with the largest key, found on a lake. Of elements with equal keys, the first is returned
+///
+/// Unlike [`Iterator::max_by_key`], which returns the last
+pub fn lake_max_by_key<C, T, K, F>(thread_count: C, data: Vec<T>, key: F) -> Option<T>
+    where C: ThreadCount, T: Send + Sync + 'static, K: Ord, F: Fn(&T) -> K + Send + Sync + 'static
+{
+    let (data, indices) = best(thread_count, data, 1, move |a, b| key(b).cmp(&key(a)));
+    take(data, &indices).pop()
+}
+
+///The index of the smallest element of `data`, found on a lake. If several are equally small, the lowest index is returned
+pub fn lake_argmin<C: ThreadCount, T: Ord + Send + Sync + 'static>(thread_count: C, data: Vec<T>) -> Option<usize> {
+    best(thread_count, data, 1, T::cmp).1.pop()
+}
+
+///The index of the largest element of `data`, found on a lake. If several are equally large, the lowest index is returned
+pub fn lake_argmax<C: ThreadCount, T: Ord + Send + Sync + 'static>(thread_count: C, data: Vec<T>) -> Option<usize> {
+    best(thread_count, data, 1, |a: &T, b: &T| b.cmp(a)).1.pop()
+}
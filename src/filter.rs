@@ -0,0 +1,106 @@
+use std::mem::MaybeUninit;
+use std::ptr;
+use crate::builder::Builder;
+use crate::disjointer::{Disjointer, into_uninit, from_uninit};
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+struct Filter<T, P> {
+    _input: Disjointer<MaybeUninit<T>>,
+    _output: Disjointer<MaybeUninit<T>>,
+    _predicate: P,
+    _keep_rejected: bool,
+}
+
+///Move the elements of `data` matching `predicate` to the front of a new vector, in order, returning it along with the
+/// number of matches. The rejected elements follow them, also in order, if `keep_rejected` is set, and are dropped otherwise
+///
+/// Each thread counts the matches in its piece, then the threads exchange their counts, so every thread knows where
+/// its matches and rejects go in the output. Since those ranges never overlap, each thread moves its elements
+/// straight into its own pieces of the output.
+fn filter<C, T, P>(thread_count: C, data: Vec<T>, predicate: P, keep_rejected: bool) -> (Vec<T>, usize)
+    where C: ThreadCount, T: Send + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    let len = data.len();
+    let threads = threads_for(thread_count, len);
+
+    if len == 0 {
+        return (Vec::new(), 0)
+    }
+
+    //Elements only ever exist once across the two buffers, and neither buffer drops them, so a panic leaks them
+    let filter = Filter {
+        _input: Disjointer::new(into_uninit(data)),
+        _output: Disjointer::new((0..len).map(|_| MaybeUninit::uninit()).collect::<Vec<_>>()),
+        _predicate: predicate,
+        _keep_rejected: keep_rejected,
+    };
+
+    let lake = Builder::with_data(threads, filter)
+        .spawn(|x: ThreadUtilities<Filter<T, P>>| {
+            let filter = x.data();
+            let mut piece = filter._input.piece(&x);
+
+            //Each element was initialised by the caller
+            let matches: Vec<bool> = piece.iter().map(|element| (filter._predicate)(unsafe { element.assume_init_ref() })).collect();
+            let count = matches.iter().filter(|matched| **matched).count();
+
+            let counts = x.all_gather(count);
+            let total: usize = counts.iter().sum();
+
+            let start = x.range(filter._input.len()).start;
+            let before: usize = counts[..x.index()].iter().sum();
+
+            let kept = unsafe { filter._output.slice_mut(before..before + count) };
+            let mut kept = kept.iter_mut();
+
+            let mut rejected = if filter._keep_rejected {
+                let rejected_before = start - before;
+                let rejected = unsafe { filter._output.slice_mut(total + rejected_before..total + rejected_before + matches.len() - count) };
+                Some(rejected.iter_mut())
+            } else {
+                None
+            };
+
+            for (element, matched) in piece.iter_mut().zip(matches) {
+                match (matched, &mut rejected) {
+                    (true, _) => unsafe { ptr::copy_nonoverlapping(element, kept.next().unwrap(), 1) },
+                    (false, Some(rejected)) => unsafe { ptr::copy_nonoverlapping(element, rejected.next().unwrap(), 1) },
+                    (false, None) => unsafe { element.assume_init_drop() },
+                }
+            }
+
+            total
+        });
+
+    let (totals, filter) = lake.join_with_data();
+    let total = totals[0];
+
+    let mut output = filter._output.take();
+
+    if !keep_rejected {
+        output.truncate(total);
+    }
+
+    //The output now holds every element kept
+    (unsafe { from_uninit(output) }, total)
+}
+
+///Collect the elements of `data` matching `predicate`, in order, on a lake
+///
+/// If `predicate` panics, the panic is passed on and the elements are leaked
+pub fn lake_filter_collect<C, T, P>(thread_count: C, data: Vec<T>, predicate: P) -> Vec<T>
+    where C: ThreadCount, T: Send + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    filter(thread_count, data, predicate, false).0
+}
+
+///Split `data` into the elements matching `predicate` and the rest, both in order, on a lake
+pub fn lake_partition<C, T, P>(thread_count: C, data: Vec<T>, predicate: P) -> (Vec<T>, Vec<T>)
+    where C: ThreadCount, T: Send + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    let (mut matching, count) = filter(thread_count, data, predicate, true);
+    let rest = matching.split_off(count);
+
+    (matching, rest)
+}
@@ -0,0 +1,92 @@
+use std::mem;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use crate::threadutilities::ThreadUtilities;
+
+///A frontier of items processed in rounds by every thread in a lake, such as the vertices at one depth of a breadth-first search
+///
+/// Declared with [`crate::builder::Builder::frontier`]. Threads get it with [`ThreadUtilities::frontier`] and call
+/// [`Frontier::expand`], which shares the current frontier out between them and collects what they discover into the
+/// next one. A visited set, marked with [`Frontier::visit`], keeps items from being discovered twice.
+pub struct Frontier<T> {
+    _current: RwLock<Vec<T>>,
+    _next: Mutex<Vec<(usize, Vec<T>)>>, //What each thread discovered in this round, tagged with its index
+    _visited: Vec<AtomicU64>,
+    _vertices: usize,
+    _round: AtomicUsize,
+}
+
+impl<T: Send + Sync> Frontier<T> {
+
+    pub (crate) fn new(start: Vec<T>, vertices: usize) -> Self {
+        Self {
+            _current: RwLock::new(start),
+            _next: Mutex::new(Vec::new()),
+            _visited: (0..vertices.div_ceil(64)).map(|_| AtomicU64::new(0)).collect(),
+            _vertices: vertices,
+            _round: AtomicUsize::new(0),
+        }
+    }
+
+    ///Process the frontier round by round until a round discovers nothing, returning the number of rounds
+    ///
+    /// Each round, every thread calls `f` on its piece of the frontier, pushing what it discovers onto a buffer of its
+    /// own. Once every thread is done, the buffers are joined in thread index order to make the next frontier. Every
+    /// thread in the lake must call this, as the rounds are separated by barriers.
+    pub fn expand<D, M, F>(&self, x: &ThreadUtilities<D, M>, mut f: F) -> usize
+        where F: FnMut(&T, &mut Vec<T>)
+    {
+        loop {
+            let mut next = Vec::new();
+
+            {
+                let current = self._current.read().unwrap();
+
+                //Every thread sees the same frontier, so they all stop together
+                if current.is_empty() {
+                    return self.round()
+                }
+
+                for item in x.split_slice(current.as_slice()) {
+                    f(item, &mut next);
+                }
+            }
+
+            self._next.lock().unwrap().push((x.index(), next));
+
+            if x.barrier() {
+                let mut discovered = mem::take(&mut *self._next.lock().unwrap());
+                discovered.sort_unstable_by_key(|(index, _)| *index);
+
+                *self._current.write().unwrap() = discovered.into_iter().flat_map(|(_, items)| items).collect();
+                self._round.fetch_add(1, Ordering::AcqRel);
+            }
+
+            x.barrier();
+        }
+    }
+
+    ///The number of rounds finished so far, which is also the depth of the frontier being processed
+    pub fn round(&self) -> usize {
+        self._round.load(Ordering::Acquire)
+    }
+
+    ///Mark `index` as visited. Returns true if this call marked it, and false if it was already visited
+    ///
+    /// Panics if `index` is not less than the number of vertices the frontier was declared with
+    pub fn visit(&self, index: usize) -> bool {
+        let bit = self.bit(index);
+        self._visited[index / 64].fetch_or(bit, Ordering::AcqRel) & bit == 0
+    }
+
+    ///Returns true if `index` has been visited
+    pub fn visited(&self, index: usize) -> bool {
+        let bit = self.bit(index);
+        self._visited[index / 64].load(Ordering::Acquire) & bit != 0
+    }
+
+    fn bit(&self, index: usize) -> u64 {
+        assert!(index < self._vertices, "vertex {} is out of range for a frontier over {} vertices", index, self._vertices);
+        1 << (index % 64)
+    }
+}
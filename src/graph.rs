@@ -0,0 +1,245 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::ThreadCount;
+
+///Identifies a node in a [`TaskGraph`], whatever the type of its output
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+///Identifies a node in a [`TaskGraph`] whose output has type `T`
+pub struct Node<T> {
+    _id: NodeId,
+    _output: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Node<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Node<T> {}
+
+impl<T> Node<T> {
+    ///The untyped id of the node, used to list it as a dependency
+    pub fn id(&self) -> NodeId {
+        self._id
+    }
+}
+
+///Why a node has no output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeError<E> {
+    ///The node returned an error
+    Failed(E),
+    ///The node panicked, with the panic message if there was one
+    Panicked(String),
+    ///The node never ran, because the given node failed. This is the node where the failure started, which may be a dependency of a dependency
+    Skipped(NodeId),
+    ///The node never ran, because the lake was stopped first
+    Cancelled,
+}
+
+type Output<E> = Result<Box<dyn Any + Send + Sync>, NodeError<E>>;
+
+type Job<E> = Box<dyn FnOnce(&Inputs<'_, E>) -> Result<Box<dyn Any + Send + Sync>, E> + Send>;
+
+///The outputs of a node's dependencies, handed to the node when it runs
+pub struct Inputs<'a, E> {
+    _outputs: & 'a [OnceLock<Output<E>>],
+    _dependencies: & 'a [usize],
+}
+
+impl<'a, E> Inputs<'a, E> {
+
+    ///Get the output of a dependency. Panics if `node` is not one of this node's dependencies
+    pub fn get<T: 'static>(&self, node: Node<T>) -> &'a T {
+        assert!(self._dependencies.contains(&node._id.0), "a node can only read the outputs of its own dependencies");
+
+        match self._outputs[node._id.0].get() {
+            Some(Ok(output)) => output.downcast_ref().unwrap(),
+            _ => unreachable!("nodes only run once all of their dependencies have succeeded"),
+        }
+    }
+}
+
+///A set of nodes with dependencies between them, run in parallel on the threads of a lake
+///
+/// Each node is a closure taking the outputs of its dependencies, and returning an output of its own or an error of type `E`.
+/// Since a node can only depend on nodes added before it, a graph never has cycles. When a node fails, every node that
+/// depends on it, directly or not, is skipped.
+pub struct TaskGraph<E = ()> {
+    _jobs: Vec<Job<E>>,
+    _dependencies: Vec<Vec<usize>>,
+}
+
+impl<E: Send + Sync + 'static> Default for TaskGraph<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Send + Sync + 'static> TaskGraph<E> {
+
+    ///Create an empty graph
+    pub fn new() -> Self {
+        Self { _jobs: Vec::new(), _dependencies: Vec::new() }
+    }
+
+    ///Add a node that runs once every node in `dependencies` has succeeded
+    pub fn add<T, F>(&mut self, dependencies: &[NodeId], node: F) -> Node<T>
+        where T: Send + Sync + 'static, F: FnOnce(&Inputs<'_, E>) -> Result<T, E> + Send + 'static
+    {
+        let id = self._jobs.len();
+
+        assert!(dependencies.iter().all(|dependency| dependency.0 < id), "dependencies must be nodes already in this graph");
+
+        self._jobs.push(Box::new(move |inputs| node(inputs).map(|output| Box::new(output) as Box<dyn Any + Send + Sync>)));
+        self._dependencies.push(dependencies.iter().map(|dependency| dependency.0).collect());
+
+        Node { _id: NodeId(id), _output: PhantomData }
+    }
+
+    ///Number of nodes in the graph
+    pub fn len(&self) -> usize {
+        self._jobs.len()
+    }
+
+    ///Returns true if the graph has no nodes
+    pub fn is_empty(&self) -> bool {
+        self._jobs.is_empty()
+    }
+
+    ///Run the graph on a new lake, returning the output of every node once they have all finished
+    ///
+    /// Nodes run as soon as their dependencies are finished, as tasks spawned on the lake's threads
+    pub fn run<C: ThreadCount>(self, thread_count: C) -> GraphOutputs<E> {
+        let lake = Builder::with_data(thread_count, Mutex::new(Some(self)))
+            .spawn(|x: ThreadUtilities<Mutex<Option<TaskGraph<E>>>>| {
+                //One thread starts the graph, and the others steal its nodes once their closures return
+                let graph = x.data().lock().unwrap().take();
+                graph.map(|graph| graph.run_on(&x))
+            });
+
+        lake.join_all().into_iter().flatten().next().expect("a graph needs at least one thread to run on")
+    }
+
+    ///Run the graph on the lake that `x` belongs to, returning the output of every node once they have all finished
+    ///
+    /// Only one thread calls this. Nodes are spawned as tasks with [`ThreadUtilities::spawn_task`], so the other threads
+    /// pick them up whenever they look for tasks, and this thread helps run them until the graph is done. Nodes that
+    /// have not started when the lake is stopped are [`NodeError::Cancelled`].
+    pub fn run_on<D, M>(self, x: &ThreadUtilities<D, M>) -> GraphOutputs<E> {
+        let count = self._jobs.len();
+
+        let mut dependents = vec![Vec::new(); count];
+        for (node, dependencies) in self._dependencies.iter().enumerate() {
+            for dependency in dependencies {
+                dependents[*dependency].push(node);
+            }
+        }
+
+        let state = Arc::new(State {
+            _waiting: self._dependencies.iter().map(|dependencies| AtomicUsize::new(dependencies.len())).collect(),
+            _jobs: self._jobs.into_iter().map(|job| Mutex::new(Some(job))).collect(),
+            _dependencies: self._dependencies,
+            _dependents: dependents,
+            _outputs: (0..count).map(|_| OnceLock::new()).collect(),
+        });
+
+        for node in 0..count {
+            if state._dependencies[node].is_empty() {
+                let state = state.clone();
+                x.spawn_task(move |x| run_node(x, &state, node));
+            }
+        }
+
+        //Every node holds on to the state until it has spawned its dependents, so the graph is done once nothing else holds it
+        x.help(|| Arc::strong_count(&state) == 1);
+
+        let state = Arc::try_unwrap(state).ok().expect("the nodes still hold the graph");
+
+        GraphOutputs { _outputs: state._outputs.into_iter().map(|output| output.into_inner().unwrap_or(Err(NodeError::Cancelled))).collect() }
+    }
+}
+
+struct State<E> {
+    _jobs: Vec<Mutex<Option<Job<E>>>>,
+    _dependencies: Vec<Vec<usize>>,
+    _dependents: Vec<Vec<usize>>,
+    _waiting: Vec<AtomicUsize>, //Number of dependencies of each node that have not finished
+    _outputs: Vec<OnceLock<Output<E>>>,
+}
+
+fn run_node<D, M, E: Send + Sync + 'static>(x: &ThreadUtilities<D, M>, state: &Arc<State<E>>, node: usize) {
+    let job = state._jobs[node].lock().unwrap().take().unwrap();
+
+    //Dependencies have all finished, so their outputs are set
+    let failed = state._dependencies[node].iter().find_map(|dependency| match state._outputs[*dependency].get() {
+        Some(Err(NodeError::Skipped(cause))) => Some(*cause),
+        Some(Err(_)) => Some(NodeId(*dependency)),
+        _ => None,
+    });
+
+    let output = match failed {
+        Some(cause) => Err(NodeError::Skipped(cause)),
+        None => {
+            let inputs = Inputs { _outputs: &state._outputs, _dependencies: &state._dependencies[node] };
+
+            match catch_unwind(AssertUnwindSafe(|| job(&inputs))) {
+                Ok(Ok(output)) => Ok(output),
+                Ok(Err(error)) => Err(NodeError::Failed(error)),
+                Err(payload) => Err(NodeError::Panicked(panic_message(payload))),
+            }
+        }
+    };
+
+    let _ = state._outputs[node].set(output);
+
+    for dependent in &state._dependents[node] {
+        let dependent = *dependent;
+
+        if state._waiting[dependent].fetch_sub(1, Ordering::AcqRel) == 1 {
+            let state = state.clone();
+            x.spawn_task(move |x| run_node(x, &state, dependent));
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map(|message| message.to_string()).unwrap_or_default(),
+    }
+}
+
+///The output of every node in a [`TaskGraph`] that has been run
+pub struct GraphOutputs<E> {
+    _outputs: Vec<Output<E>>,
+}
+
+impl<E> GraphOutputs<E> {
+
+    ///Get the output of a node, or the reason it has none
+    pub fn get<T: 'static>(&self, node: Node<T>) -> Result<&T, &NodeError<E>> {
+        match &self._outputs[node._id.0] {
+            Ok(output) => Ok(output.downcast_ref().expect("the node belongs to a different graph")),
+            Err(error) => Err(error),
+        }
+    }
+
+    ///Get the reason a node has no output, by its untyped id
+    pub fn error(&self, node: NodeId) -> Option<&NodeError<E>> {
+        self._outputs[node.0].as_ref().err()
+    }
+
+    ///Returns true if every node in the graph succeeded
+    pub fn all_succeeded(&self) -> bool {
+        self._outputs.iter().all(|output| output.is_ok())
+    }
+}
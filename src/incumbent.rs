@@ -0,0 +1,97 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use crate::traits::Score;
+
+///Whether a smaller or a larger score is better
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    Minimize,
+    Maximize,
+}
+
+///The best solution found so far by any thread in a lake
+///
+/// Declared with [`crate::builder::Builder::incumbent`]. Threads offer candidate solutions with [`Incumbent::offer`] and prune
+/// their search against [`Incumbent::best_score`] or [`Incumbent::improves`]. Offers that do not beat the incumbent, which is
+/// almost all of them once a search is under way, are rejected without taking a lock.
+pub struct Incumbent<S, V> {
+    _objective: Objective,
+    _best: AtomicU64, //Goodness of the best score, higher is better. Only meaningful while `_held` is set
+    _held: AtomicBool, //Set once there is an incumbent, since every goodness, zero included, can be a real score
+    _value: Mutex<Option<(S, V)>>,
+}
+
+impl<S: Score, V> Incumbent<S, V> {
+
+    pub (crate) fn new(objective: Objective) -> Self {
+        Self { _objective: objective, _best: AtomicU64::new(0), _held: AtomicBool::new(false), _value: Mutex::new(None) }
+    }
+
+    fn goodness(&self, score: S) -> u64 {
+        match self._objective {
+            Objective::Minimize => !score.to_key(),
+            Objective::Maximize => score.to_key(),
+        }
+    }
+
+    fn score(&self, goodness: u64) -> S {
+        match self._objective {
+            Objective::Minimize => S::from_key(!goodness),
+            Objective::Maximize => S::from_key(goodness),
+        }
+    }
+
+    ///Returns true if `score` would replace the incumbent. Ties keep the incumbent
+    pub fn improves(&self, score: S) -> bool {
+        //The best goodness is stored before the flag is set, so it is never older than the flag
+        !self._held.load(Ordering::Acquire) || self.goodness(score) > self._best.load(Ordering::Acquire)
+    }
+
+    ///Offer a solution, keeping it if it beats the incumbent. Returns true if it was kept
+    pub fn offer(&self, score: S, value: V) -> bool {
+        let goodness = self.goodness(score);
+
+        if self._held.load(Ordering::Acquire) && goodness <= self._best.load(Ordering::Acquire) {
+            return false
+        }
+
+        let mut incumbent = self._value.lock().unwrap();
+
+        //Another thread may have got here first with a better offer
+        if let Some((best, _)) = incumbent.as_ref() {
+            if goodness <= self.goodness(*best) {
+                return false
+            }
+        }
+
+        *incumbent = Some((score, value));
+        self._best.store(goodness, Ordering::Release);
+        self._held.store(true, Ordering::Release);
+
+        true
+    }
+
+    ///The score of the incumbent, if there is one
+    pub fn best_score(&self) -> Option<S> {
+        if self._held.load(Ordering::Acquire) {
+            Some(self.score(self._best.load(Ordering::Acquire)))
+        } else {
+            None
+        }
+    }
+
+    ///A copy of the incumbent score and value
+    pub fn best(&self) -> Option<(S, V)>
+        where V: Clone
+    {
+        self._value.lock().unwrap().clone()
+    }
+
+    ///Take the incumbent out, usually once the lake has been joined
+    pub fn take(&self) -> Option<(S, V)> {
+        let mut slot = self._value.lock().unwrap();
+        self._held.store(false, Ordering::Release);
+        self._best.store(0, Ordering::Release);
+        slot.take()
+    }
+}
@@ -0,0 +1,143 @@
+use crate::builder::Builder;
+use crate::disjointer::Disjointer;
+use crate::threadlake::ThreadLake;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+type Step<T> = Box<dyn Fn(usize, &[T]) -> (T, f64) + Send + Sync>;
+type Converged = Box<dyn Fn(f64) -> bool + Send + Sync>;
+
+///The state being iterated, kept in two buffers so each iteration can read the last one while writing the next
+struct Iterate<T> {
+    _buffers: [Disjointer<T>; 2],
+    _step: Step<T>,
+    _converged: Converged,
+    _max_iters: usize,
+}
+
+///What the threads agree on once they stop: the number of iterations, the last residual, and whether it converged
+type Finished = (usize, f64, bool);
+
+///An iterative computation running on a lake, started with [`lake_iterate`]
+pub struct Iteration<T> {
+    _lake: ThreadLake<Iterate<T>, Finished>,
+}
+
+impl<T: Send + Sync + 'static> Iteration<T> {
+
+    ///Ask the threads to stop after the iteration they are working on
+    pub fn stop(&self) {
+        self._lake.stop()
+    }
+
+    ///Wait for the iterations to finish, and get back the final state
+    ///
+    /// If `step` or `converged` panicked, the panic is passed on
+    pub fn join(self) -> Iterated<T> {
+        let (finished, iterate) = self._lake.join_with_data();
+        let (iterations, residual, converged) = finished[0];
+
+        let [even, odd] = iterate._buffers;
+
+        //The buffers swap roles every iteration, starting with the state in the first
+        let state = if iterations % 2 == 0 { even } else { odd };
+
+        Iterated { _state: state.take(), _iterations: iterations, _residual: residual, _converged: converged }
+    }
+}
+
+///The result of an [`Iteration`]
+pub struct Iterated<T> {
+    _state: Vec<T>,
+    _iterations: usize,
+    _residual: f64,
+    _converged: bool,
+}
+
+impl<T> Iterated<T> {
+
+    ///The state after the last iteration
+    pub fn state(&self) -> &[T] {
+        &self._state
+    }
+
+    ///Take the state after the last iteration
+    pub fn into_state(self) -> Vec<T> {
+        self._state
+    }
+
+    ///The number of iterations run
+    pub fn iterations(&self) -> usize {
+        self._iterations
+    }
+
+    ///The residual of the last iteration, or infinity if none were run
+    pub fn residual(&self) -> f64 {
+        self._residual
+    }
+
+    ///Returns true if the iterations stopped because `converged` accepted the residual
+    pub fn converged(&self) -> bool {
+        self._converged
+    }
+}
+
+///Repeatedly compute a new state from the old one on a lake, until `converged` accepts the residual or `max_iters` iterations have run
+///
+/// Each iteration, `step` is given the index of an element and the whole previous state, and returns the element's new
+/// value along with its residual, such as how far it moved. Every thread computes its own piece of the new state, and
+/// the residual of the iteration, the largest of any element, is shared between all of them so they all stop together.
+/// The same threads are used for every iteration. Calling [`Iteration::stop`] stops the threads after the iteration
+/// they are working on.
+pub fn lake_iterate<C, T, S, V>(thread_count: C, state: Vec<T>, step: S, converged: V, max_iters: usize) -> Iteration<T>
+    where C: ThreadCount, T: Clone + Send + Sync + 'static,
+          S: Fn(usize, &[T]) -> (T, f64) + Send + Sync + 'static, V: Fn(f64) -> bool + Send + Sync + 'static
+{
+    let threads = threads_for(thread_count, state.len());
+
+    let iterate = Iterate {
+        _buffers: [Disjointer::new(state.clone()), Disjointer::new(state)],
+        _step: Box::new(step),
+        _converged: Box::new(converged),
+        _max_iters: max_iters,
+    };
+
+    let lake = Builder::with_data(threads, iterate)
+        .spawn(|x: ThreadUtilities<Iterate<T>>| {
+            let iterate = x.data();
+            let len = iterate._buffers[0].len();
+
+            let mut iterations = 0;
+            let mut residual = f64::INFINITY;
+
+            while iterations < iterate._max_iters {
+                //Each buffer is only written by one iteration and read by the next, with a barrier in between
+                let previous = unsafe { iterate._buffers[iterations % 2].slice(0..len) };
+                let mut next = iterate._buffers[(iterations + 1) % 2].piece(&x);
+
+                let mut local = 0.0f64;
+                for (index, element) in x.range(len).zip(next.iter_mut()) {
+                    let (value, residual) = (iterate._step)(index, previous);
+                    *element = value;
+                    local = local.max(residual);
+                }
+
+                //Every thread gets the same residuals and stop signals, so every thread makes the same decision
+                let gathered = x.all_gather((local, x.check()));
+                residual = gathered.iter().fold(0.0, |residual, (local, _)| residual.max(*local));
+                iterations += 1;
+
+                if (iterate._converged)(residual) {
+                    return (iterations, residual, true)
+                }
+
+                if gathered.iter().any(|(_, stopped)| *stopped) {
+                    break
+                }
+            }
+
+            (iterations, residual, false)
+        });
+
+    Iteration { _lake: lake }
+}
@@ -13,6 +13,70 @@ pub mod traits;
 ///Object used to mutably access a vector by multiple threads simultaneously
 pub mod disjointer;
 
+///Typed channels for sending several kinds of message from threads to the lake
+pub mod channels;
+
+///The best solution found so far, shared between threads for branch-and-bound and anytime searches
+pub mod incumbent;
+
+///Dynamic and guided schedules for loops with uneven work
+pub mod schedule;
+
+///Graphs of dependent tasks, run in parallel on a lake
+pub mod graph;
+
+///Map/reduce jobs, with pairs shuffled between threads by key
+pub mod mapreduce;
+
+///Parallel versions of common iterator operations on vectors and slices
+pub mod parallel;
+
+///Parallel maps over iterators of unknown length, yielding results in order
+pub mod ordered;
+
+///Searches for the first or last match, which stop early once no better match can be found
+pub mod search;
+
+///Parallel sorts, which sort a piece on each thread and then merge the pieces in parallel
+pub mod sort;
+
+///Parallel inclusive and exclusive prefix scans, in place
+pub mod scan;
+
+///Parallel filtering and partitioning into new vectors
+pub mod filter;
+
+///Histograms and group-by aggregations, folded into per-thread partials
+pub mod aggregate;
+
+///Reductions that give the same answer, to the bit, whatever the number of threads
+pub mod deterministic;
+
+///Top-k, minimum, maximum, argmin and argmax, with ties broken by the lowest index
+pub mod extrema;
+
+///Parallel removal of duplicates, with elements sent to the thread that owns their hash
+pub mod unique;
+
+///Frontiers processed round by round, for breadth-first searches and other level-synchronous traversals
+pub mod frontier;
+
+///Iterative solvers that repeat a parallel step until the residual converges
+pub mod iterate;
+
+///Chains of lakes connected by bounded queues, for stage-parallel processing
+pub mod pipeline;
+
+///Counter-based random number streams, reproducible at any thread count
+pub mod rng;
+
+mod barrier;
+mod queue;
+mod router;
+mod tasks;
+mod registry;
+mod watch;
+
 ///Objects encapsulating a subslice created from the [`threadutilities::ThreadUtilities`] functions
 pub mod split;
 
@@ -25,7 +89,25 @@ mod tests {
     use crate::builder::Builder;
     use crate::disjointer::Disjointer;
     use crate::traits::FullParallelism;
-    use std::sync::Mutex;
+    use std::sync::{Mutex, Arc};
+    use crate::channels::Overflow;
+    use crate::incumbent::Objective;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use crate::graph::{TaskGraph, NodeError};
+    use crate::pipeline::Pipeline;
+    use crate::parallel::LakeIterExt;
+    use crate::ordered::lake_map_ordered;
+    use crate::search::{lake_position, lake_find_first, lake_find_last};
+    use crate::sort::{lake_sort, lake_sort_by, lake_sort_by_key, lake_sort_unstable};
+    use crate::scan::{lake_scan_inclusive, lake_scan_exclusive};
+    use crate::filter::{lake_filter_collect, lake_partition};
+    use crate::aggregate::{lake_histogram, lake_histogram_sparse, lake_group_by};
+    use crate::deterministic::{lake_sum_deterministic, lake_reduce_deterministic, Summation};
+    use crate::extrema::{lake_top_k, lake_min_by_key, lake_max_by_key, lake_argmin, lake_argmax};
+    use crate::unique::{lake_unique, lake_dedup_by_key, UniqueOrder};
+    use crate::iterate::lake_iterate;
+    use crate::rng::Rng;
+    use crate::mapreduce::{map_reduce, MapReduce, Emitter};
 
     #[test]
     fn hello_lakes() {
@@ -106,6 +188,41 @@ mod tests {
 
     }
 
+    #[test]
+    fn pause_and_play() {
+
+        let lake = Builder::with_data(3, Mutex::new(0usize))
+            .spawn(|x: ThreadUtilities<Mutex<usize>>| {
+                //Each pass counts one step, and check blocks the thread while it is paused
+                while !x.check() {
+                    *x.data().lock().unwrap() += 1;
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            });
+
+        let counter = lake.arc();
+        let count = || *counter.lock().unwrap();
+
+        lake.pause();
+
+        //Give every thread time to finish the step it was on and reach check
+        std::thread::sleep(Duration::from_millis(100));
+        let paused = count();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(count(), paused);
+
+        lake.play();
+
+        while count() == paused {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        //A paused thread also wakes up for the stop signal
+        lake.pause();
+        lake.stop();
+        lake.join();
+    }
+
     #[test]
     fn simple_messages() {
 
@@ -303,4 +420,1187 @@ mod tests {
 
     }
 
+    #[test]
+    fn typed_channels() {
+        struct Progress(usize);
+        struct Hit(usize);
+
+        let lake = Builder::new(4)
+            .channel::<Progress>()
+            .channel::<Hit>()
+            .spawn(|x: ThreadUtilities<_>| {
+                for i in x.range(100) {
+                    if i % 10 == 0 {
+                        x.post(Progress(i)).unwrap();
+                    }
+                    if i % 25 == 0 {
+                        x.post(Hit(i)).unwrap();
+                    }
+                }
+            });
+
+        let mut progress = 0;
+        let mut hits = Vec::new();
+
+        {
+            let mut select = lake.select()
+                .recv(|p: Progress| progress += p.0)
+                .recv(|h: Hit| hits.push(h.0));
+
+            //Select fails once every thread has finished and both channels are drained
+            while select.wait().is_ok() {}
+        }
+
+        hits.sort();
+
+        assert_eq!(progress, 450);
+        assert_eq!(hits, vec![0, 25, 50, 75]);
+
+        lake.join();
+    }
+
+    #[test]
+    fn bounded_channel_drop_oldest() {
+        let lake = Builder::new(1)
+            .bounded_channel::<usize>(3, Overflow::DropOldest)
+            .spawn(|x: ThreadUtilities<_>| {
+                for i in 0..10usize {
+                    x.post(i).unwrap();
+                }
+            });
+
+        let receiver = lake.channel::<usize>().unwrap();
+
+        //Wait for the thread to overflow the channel, so it holds only the newest messages
+        while receiver.dropped() < 7 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let received: Vec<_> = std::iter::from_fn(|| receiver.recv().ok()).collect();
+
+        assert_eq!(received, vec![7, 8, 9]);
+        assert_eq!(receiver.dropped(), 7);
+        assert!(lake.channel::<String>().is_none());
+
+        lake.join();
+    }
+
+    #[test]
+    fn watch_updates() {
+        struct Threshold(usize);
+
+        let lake = Builder::new(3)
+            .watch(Threshold(0))
+            .spawn(|x: ThreadUtilities<_>| {
+                let first = x.current::<Threshold>();
+
+                assert!(!x.changed::<Threshold>());
+
+                x.send(()).unwrap();
+
+                //Wait for the lake to publish a new threshold
+                while !x.changed::<Threshold>() {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+
+                //The old snapshot is untouched by the publish
+                (first.0, x.current::<Threshold>().0)
+            });
+
+        //Publish once every thread has read the first version
+        for _ in 0..lake.max_threads() {
+            lake.receiver().recv().unwrap();
+        }
+
+        lake.publish(Threshold(42));
+
+        assert!(lake.join_iter().all(|x| x.unwrap() == (0, 42)));
+    }
+
+    #[test]
+    fn incumbent_search() {
+        let points: Vec<f64> = (0..100000).map(|i| ((i * 7919) % 100003) as f64 - 50000.5).collect();
+
+        let lake = Builder::with_data(FullParallelism, points.clone())
+            .incumbent::<f64, usize>(Objective::Minimize)
+            .spawn(|x: ThreadUtilities<_>| {
+                let incumbent = x.incumbent::<f64, usize>();
+                let offset = x.range(x.data().len()).start;
+
+                for (i, point) in x.split_slice(x.data()).iter().enumerate() {
+                    let distance = (point - 1234.25).abs();
+
+                    if incumbent.improves(distance) {
+                        incumbent.offer(distance, i + offset);
+                    }
+                }
+            });
+
+        let incumbent = lake.incumbent::<f64, usize>();
+
+        lake.join();
+
+        let expected = points.iter().enumerate().min_by(|a, b| (a.1 - 1234.25).abs().total_cmp(&(b.1 - 1234.25).abs())).unwrap();
+
+        assert_eq!(incumbent.best_score(), Some((expected.1 - 1234.25).abs()));
+        assert_eq!(incumbent.take().map(|(_, index)| index), Some(expected.0));
+        assert_eq!(incumbent.best_score(), None);
+    }
+
+    #[test]
+    fn incumbent_zero_score() {
+        //The worst possible score is still an incumbent, and any better score improves on it
+        let lake = Builder::new(1)
+            .incumbent::<u64, &'static str>(Objective::Maximize)
+            .incumbent::<i64, &'static str>(Objective::Minimize)
+            .spawn(|x: ThreadUtilities<_>| {
+                let maximize = x.incumbent::<u64, &'static str>();
+                assert!(maximize.offer(0, "zero"));
+                assert_eq!(maximize.best_score(), Some(0));
+                assert!(!maximize.improves(0));
+                assert!(maximize.improves(5));
+                assert!(maximize.offer(5, "five"));
+
+                let minimize = x.incumbent::<i64, &'static str>();
+                assert!(minimize.offer(i64::MAX, "max"));
+                assert!(!minimize.offer(i64::MAX, "max again"));
+                assert!(minimize.improves(5));
+                assert!(minimize.offer(5, "five"));
+            });
+
+        let maximize = lake.incumbent::<u64, &'static str>();
+        lake.join();
+
+        assert_eq!(maximize.take(), Some((5, "five")));
+        assert!(maximize.improves(0));
+    }
+
+    #[test]
+    fn work_queue() {
+        let lake: ThreadLake<_, u64> = Builder::new(4)
+            .bounded_queue::<u64>(16)
+            .spawn(|x: ThreadUtilities<_>| {
+                let mut total = 0;
+
+                while let Some(item) = x.next_item::<u64>() {
+                    total += item;
+                }
+
+                total
+            });
+
+        lake.submit_all(1..=1000u64).unwrap();
+
+        //Joining closes the queue, so the threads finish once it is drained
+        assert_eq!(lake.join_iter().map(|x| x.unwrap()).sum::<u64>(), 500500);
+    }
+
+    #[test]
+    fn work_queue_stop() {
+        let lake = Builder::new(3)
+            .queue::<String>()
+            .spawn(|x: ThreadUtilities<_>| {
+                x.send(()).unwrap();
+                x.next_item::<String>()
+            });
+
+        for _ in 0..lake.max_threads() {
+            lake.receiver().recv().unwrap();
+        }
+
+        //Nothing is ever submitted, so the threads only finish because of the stop signal
+        lake.stop();
+
+        assert!(lake.join_iter().all(|x| x.unwrap().is_none()));
+    }
+
+    #[test]
+    fn work_queue_drop() {
+        let lake = Builder::with_data(3, AtomicUsize::new(0))
+            .queue::<u64>()
+            .spawn(|x: ThreadUtilities<AtomicUsize>| {
+                while x.next_item::<u64>().is_some() {}
+                x.data().fetch_add(1, Ordering::SeqCst);
+            });
+
+        lake.submit_all(0..100u64).unwrap();
+        let finished = lake.arc();
+
+        //Dropping the lake without joining it still closes the queue, so the threads do not wait forever
+        drop(lake);
+
+        while finished.load(Ordering::SeqCst) < 3 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn dynamic_schedule_large_chunks() {
+        let lake = Builder::new(4)
+            .spawn(|x: ThreadUtilities<_>| {
+                let mut schedule = x.dynamic_range(10, usize::MAX);
+                let claimed: usize = schedule.by_ref().map(|range| range.len()).sum();
+
+                //Calling again once the schedule is used up must not wrap the cursor back into range
+                for _ in 0..4 {
+                    assert!(schedule.next().is_none());
+                }
+
+                claimed
+            });
+
+        assert_eq!(lake.join_iter().map(|x| x.unwrap()).sum::<usize>(), 10);
+    }
+
+    #[test]
+    fn dynamic_schedules_reclaimed() {
+        let lake = Builder::new(3)
+            .spawn(|x: ThreadUtilities<_>| {
+                //As in an iterative solver, every iteration starts a fresh schedule
+                let claimed: usize = (0..1000).map(|_| x.dynamic_range(30, 4).map(|range| range.len()).sum::<usize>()).sum();
+
+                //Once every thread has started every schedule, none of their cursors are kept
+                x.barrier();
+                assert_eq!(x._cursors.len(), 0);
+
+                claimed
+            });
+
+        assert_eq!(lake.join_iter().map(|x| x.unwrap()).sum::<usize>(), 30_000);
+    }
+
+    #[test]
+    fn dynamic_schedules() {
+        let n = 100000;
+
+        let lake = Builder::with_data(4, (Disjointer::new(vec![0usize; n]), (0..n).collect::<Vec<_>>()))
+            .spawn(move |x: ThreadUtilities<_>| {
+                let (disjointer, values) = x.data();
+
+                //Each element is given to exactly one thread, whichever claims its chunk first. Every thread starts this schedule first
+                for mut piece in unsafe { disjointer.pieces(x.guided_range(n, 16)) } {
+                    for element in piece.iter_mut() {
+                        *element += 1;
+                    }
+                }
+
+                x.dynamic_range(n, 1000).slices(values).map(|subslice| subslice.iter().sum::<usize>()).sum::<usize>()
+            });
+
+        let data = lake.arc();
+
+        assert_eq!(lake.join_iter().map(|x| x.unwrap()).sum::<usize>(), n * (n - 1) / 2);
+
+        let (disjointer, _) = Arc::try_unwrap(data).ok().unwrap();
+
+        assert!(disjointer.take().iter().all(|x| *x == 1));
+    }
+
+    fn quicksort<D, M>(x: &ThreadUtilities<D, M>, v: &mut [u64]) {
+        if v.len() <= 32 {
+            v.sort();
+            return
+        }
+
+        let last = v.len() - 1;
+        v.swap(v.len() / 2, last);
+
+        let mut store = 0;
+        for i in 0..last {
+            if v[i] < v[last] {
+                v.swap(i, store);
+                store += 1;
+            }
+        }
+        v.swap(store, last);
+
+        let (left, right) = v.split_at_mut(store);
+
+        x.join(|x| quicksort(x, left), |x| quicksort(x, &mut right[1..]));
+    }
+
+    #[test]
+    fn fork_join() {
+        let v: Vec<u64> = (0..200000).map(|i| (i * 2654435761) % 1000003).collect();
+
+        let lake = Builder::with_data(4, Mutex::new(v))
+            .spawn(|x: ThreadUtilities<_>| {
+                //Only the first thread starts sorting, the others steal the forked halves from it
+                if x.index() == 0 {
+                    quicksort(&x, &mut x.data().lock().unwrap());
+                }
+            });
+
+        let v = lake.join().unwrap().into_inner().unwrap();
+
+        assert_eq!(v.len(), 200000);
+        assert!(v.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    fn spawn_tree(x: &ThreadUtilities<AtomicUsize>, depth: usize) {
+        x.data().fetch_add(1, Ordering::Relaxed);
+
+        if depth > 0 {
+            x.spawn_task(move |x| spawn_tree(x, depth - 1));
+            x.spawn_task(move |x| spawn_tree(x, depth - 1));
+        }
+    }
+
+    #[test]
+    fn spawned_tasks() {
+        let lake = Builder::with_data(4, AtomicUsize::new(0))
+            .spawn(|x: ThreadUtilities<_>| {
+                if x.index() == 0 {
+                    spawn_tree(&x, 10);
+                }
+            });
+
+        //Every task has run by the time the lake is joined
+        assert_eq!(lake.join().unwrap().into_inner(), 2047);
+    }
+
+    #[test]
+    fn spawned_tasks_stop() {
+        let lake = Builder::with_data(4, AtomicUsize::new(0))
+            .spawn(|x: ThreadUtilities<_>| {
+                while !x.check() {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+
+                //The lake is already stopped, so none of these tasks run
+                if x.index() == 0 {
+                    spawn_tree(&x, 10);
+                }
+            });
+
+        lake.stop();
+
+        //Only the root of the tree counts, as it is called directly
+        assert_eq!(lake.join().unwrap().into_inner(), 1);
+    }
+
+    #[test]
+    fn spawned_tasks_share_thread_state() {
+        struct Version;
+
+        let lake = Builder::with_data(1, AtomicUsize::new(0))
+            .watch(Version)
+            .spawn(|x: ThreadUtilities<_>| {
+                x.current::<Version>();
+                x.dynamic_range(10, 1).for_each(drop);
+
+                //With one thread, this task runs after the closure returns, and sees the same state the closure left
+                x.spawn_task(|x: &ThreadUtilities<AtomicUsize>| {
+                    assert!(!x.changed::<Version>());
+                    x.data().store(x.dynamic_range(10, 1).count(), Ordering::SeqCst);
+                });
+            });
+
+        //The task's schedule is the second one this thread started, so it claims from a fresh cursor
+        assert_eq!(lake.join().unwrap().into_inner(), 10);
+    }
+
+    #[test]
+    fn early_exit_without_tasks() {
+        struct Exited(Arc<AtomicBool>);
+
+        impl Drop for Exited {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        thread_local! {
+            static EXITED: std::cell::RefCell<Option<Exited>> = const { std::cell::RefCell::new(None) };
+        }
+
+        let lake = Builder::with_data(2, Arc::new(AtomicBool::new(false)))
+            .spawn(|x: ThreadUtilities<Arc<AtomicBool>>| {
+                if x.index() == 0 {
+                    //Set when this thread exits, as thread locals are dropped then
+                    EXITED.with(|exited| *exited.borrow_mut() = Some(Exited(x.data().clone())));
+                    return true
+                }
+
+                //Nothing is ever forked or spawned, so the first thread exits without waiting for this one
+                let start = std::time::Instant::now();
+                while !x.data().load(Ordering::SeqCst) && start.elapsed() < Duration::from_secs(5) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+
+                x.data().load(Ordering::SeqCst)
+            });
+
+        assert!(lake.join_iter().all(|x| x.unwrap()));
+    }
+
+    #[test]
+    fn task_graph() {
+        let mut graph = TaskGraph::<String>::new();
+
+        let a = graph.add(&[], |_| Ok(6u64));
+        let b = graph.add(&[], |_| Ok(7u64));
+        let product = graph.add(&[a.id(), b.id()], move |inputs| Ok(inputs.get(a) * inputs.get(b)));
+        let label = graph.add(&[product.id()], move |inputs| Ok(format!("answer: {}", inputs.get(product))));
+
+        let broken = graph.add(&[a.id()], |_| -> Result<u64, String> { Err(String::from("broken")) });
+        let dependent = graph.add(&[broken.id(), product.id()], |_| Ok(0u64));
+        let transitive = graph.add(&[dependent.id()], |_| Ok(0u64));
+        let panicked = graph.add(&[], |_| -> Result<u64, String> { panic!("This panic is deliberate, used to test that panicking nodes are reported") });
+
+        let outputs = graph.run(4);
+
+        assert_eq!(outputs.get(product), Ok(&42));
+        assert_eq!(outputs.get(label).map(|s| s.as_str()), Ok("answer: 42"));
+        assert_eq!(outputs.get(broken), Err(&NodeError::Failed(String::from("broken"))));
+        assert_eq!(outputs.get(dependent), Err(&NodeError::Skipped(broken.id())));
+        assert_eq!(outputs.get(transitive), Err(&NodeError::Skipped(broken.id())));
+        assert!(matches!(outputs.get(panicked), Err(NodeError::Panicked(_))));
+        assert!(!outputs.all_succeeded());
+    }
+
+    #[test]
+    fn task_graph_on_lake() {
+        let lake = Builder::new(4)
+            .spawn(|x: ThreadUtilities<()>| {
+                if x.index() != 0 {
+                    return None
+                }
+
+                //The graph's nodes run as tasks on the threads of this lake
+                let mut graph = TaskGraph::<()>::new();
+                let leaves: Vec<_> = (0..100u64).map(|i| graph.add(&[], move |_| Ok(i))).collect();
+                let ids: Vec<_> = leaves.iter().map(|leaf| leaf.id()).collect();
+                let sum = graph.add(&ids, move |inputs| Ok(leaves.iter().map(|leaf| *inputs.get(*leaf)).sum::<u64>()));
+
+                let outputs = graph.run_on(&x);
+
+                Some(*outputs.get(sum).unwrap())
+            });
+
+        assert_eq!(lake.join_iter().filter_map(|x| x.unwrap()).collect::<Vec<_>>(), [4950]);
+
+        //Nodes that never started because the lake was stopped are cancelled
+        let lake = Builder::new(2)
+            .spawn(|x: ThreadUtilities<()>| {
+                while !x.check() {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+
+                let mut graph = TaskGraph::<()>::new();
+                let node = graph.add(&[], |_| Ok(1u64));
+
+                graph.run_on(&x).get(node) == Err(&NodeError::Cancelled)
+            });
+
+        lake.stop();
+        assert!(lake.join_iter().all(|x| x.unwrap()));
+    }
+
+    #[test]
+    fn pipeline() {
+        let mut doubled: Vec<u64> = Pipeline::source(8, 1..=1000u64)
+            .stage(Builder::new(3), |x| x.to_string())
+            .stage(Builder::new(2), |s: String| s.parse::<u64>().unwrap() * 2)
+            .collect();
+
+        doubled.sort();
+        assert_eq!(doubled, (1..=1000u64).map(|x| x * 2).collect::<Vec<_>>());
+
+        //A pipeline over an endless source finishes once it is stopped
+        let mut endless = Pipeline::source(4, 0u64..).stage(Builder::new(2), |x| x + 1);
+        assert!(endless.next().is_some());
+        endless.stop();
+        assert!(endless.join());
+
+        //Panics in the source or in a stage end the pipeline and are passed on, rather than leaving it waiting forever
+        let panicking_source = (0..10u64).inspect(|x| assert_ne!(*x, 5, "the source failed"));
+        let collected = std::panic::catch_unwind(|| Pipeline::source(2, panicking_source).stage(Builder::new(2), |x| x).collect::<Vec<_>>());
+        assert!(collected.is_err());
+
+        let collected = std::panic::catch_unwind(|| {
+            Pipeline::source(2, 0..10u64).stage(Builder::new(3), |x| { assert_ne!(x, 5, "the stage failed"); x }).collect::<Vec<_>>()
+        });
+        assert!(collected.is_err());
+
+        let empty = std::panic::catch_unwind(|| Pipeline::source(2, 0..10u64).stage(Builder::new(0), |x| x));
+        assert!(empty.is_err());
+    }
+
+    #[test]
+    fn routed_shards() {
+        use std::collections::HashMap;
+
+        //Each message is a word and a number of hops left. Words are counted by their owner, which passes the word on under a new key
+        let lake = Builder::new(4)
+            .router::<(String, usize)>()
+            .spawn(|x: ThreadUtilities<()>| {
+                let mut shard = HashMap::new();
+
+                while let Some(message) = x.next_routed::<(String, usize)>() {
+                    assert_eq!(x.owner::<_, (String, usize)>(&message), x.index());
+
+                    let (word, hops) = message;
+                    *shard.entry(word.clone()).or_insert(0) += 1;
+
+                    if hops > 0 {
+                        let next = (word, hops - 1);
+                        x.route(&next.clone(), next);
+                    }
+                }
+
+                shard
+            });
+
+        for word in ["apple", "pear", "plum", "apple", "fig", "apple"] {
+            let message = (String::from(word), 2usize);
+            lake.route(&message.clone(), message).unwrap();
+        }
+
+        lake.close_router::<(String, usize)>();
+        assert!(lake.route(&0, (String::from("late"), 0usize)).is_err());
+
+        let mut counts = HashMap::new();
+        for shard in lake.join_iter() {
+            for (word, count) in shard.unwrap() {
+                *counts.entry(word).or_insert(0) += count;
+            }
+        }
+
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts["apple"], 9);
+        assert_eq!(counts["fig"], 3);
+    }
+
+    #[test]
+    fn barrier() {
+        let arrived = Arc::new(AtomicUsize::new(0));
+
+        let lake = Builder::with_data(4, arrived.clone())
+            .spawn(|x: ThreadUtilities<Arc<AtomicUsize>>| {
+                let mut leaders = 0;
+
+                for phase in 1..=3 {
+                    x.data().fetch_add(1, Ordering::SeqCst);
+
+                    if x.barrier() {
+                        leaders += 1;
+                    }
+
+                    assert!(x.data().load(Ordering::SeqCst) >= phase * 4);
+
+                    x.barrier();
+                }
+
+                leaders
+            });
+
+        let leaders: usize = lake.join_iter().map(|leaders| leaders.unwrap()).sum();
+        assert_eq!(leaders, 3);
+
+        //Threads waiting at the barrier panic too, rather than waiting for a thread that never arrives
+        let lake = Builder::new(3)
+            .spawn(|x: ThreadUtilities<()>| {
+                if x.index() == 0 {
+                    panic!("This panic is deliberate, used to test that barriers are poisoned");
+                }
+
+                x.barrier();
+            });
+
+        assert!(lake.join_iter().all(|result| result.is_err()));
+    }
+
+    #[test]
+    fn word_count() {
+        let lines: Vec<String> = (0..200).map(|i| format!("the quick brown fox {}", if i % 2 == 0 { "jumps" } else { "sleeps" })).collect();
+
+        let count = |line: &String, out: &mut Emitter<'_, String, usize>| {
+            for word in line.split_whitespace() {
+                out.emit(word.to_string(), 1);
+            }
+        };
+
+        let counts = map_reduce(4, lines.clone(), count, |a, b| a + b);
+
+        assert_eq!(counts.len(), 6);
+        assert_eq!(counts["the"], 200);
+        assert_eq!(counts["jumps"], 100);
+
+        let combined = MapReduce::new(count, |a, b| a + b)
+            .combiner(|a, b| a + b)
+            .run(3, lines);
+
+        assert_eq!(combined, counts);
+    }
+
+    #[test]
+    fn lake_iter() {
+        let numbers: Vec<u64> = (1..=1000).collect();
+
+        let squares = numbers.clone().lake_map(|x| x * x).lake_collect();
+        assert_eq!(squares, numbers.iter().map(|x| x * x).collect::<Vec<_>>());
+
+        let odd = numbers.as_slice().into_lake().threads(3).lake_filter(|x| x % 2 == 1).lake_collect();
+        assert_eq!(odd, numbers.iter().copied().filter(|x| x % 2 == 1).collect::<Vec<_>>());
+
+        assert_eq!(numbers.as_slice().lake_sum::<u64>(), 500500);
+        assert!(numbers.as_slice().lake_any(|x| x == 999));
+        assert!(!numbers.as_slice().lake_all(|x| x < 1000));
+
+        //Reduction keeps the order of the items, so it works for operations that are not commutative
+        let words: Vec<String> = (0..100).map(|x| x.to_string()).collect();
+        assert_eq!(words.clone().into_lake().threads(7).lake_reduce(|a, b| a + &b), Some(words.concat()));
+        assert_eq!(Vec::<String>::new().lake_reduce(|a, b| a + &b), None);
+
+        let total = Arc::new(AtomicUsize::new(0));
+        let counter = total.clone();
+        vec![1usize; 100].lake_for_each(move |x| { counter.fetch_add(x, Ordering::SeqCst); });
+        assert_eq!(total.load(Ordering::SeqCst), 100);
+    }
+
+    #[test]
+    fn ordered_map() {
+        let lines = (0..10_000u64).map(|x| x.to_string());
+
+        //Uneven work makes batches finish out of order
+        let parsed: Vec<u64> = lake_map_ordered(lines, |line: String| {
+            let x: u64 = line.parse().unwrap();
+            if x.is_multiple_of(700) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            x * 3
+        })
+            .threads(4)
+            .batch_size(16)
+            .window(6)
+            .collect();
+
+        assert_eq!(parsed, (0..10_000u64).map(|x| x * 3).collect::<Vec<_>>());
+
+        assert_eq!(lake_map_ordered(Vec::<u64>::new(), |x| x).count(), 0);
+
+        //A thread count of zero still maps every item, on one thread
+        assert_eq!(lake_map_ordered(0..10u64, |x| x * 2).threads(0).collect::<Vec<_>>(), (0..10u64).map(|x| x * 2).collect::<Vec<_>>());
+
+        //Dropping the iterator part way through stops the lake, so an endless input is only mapped as far as the window reached
+        let mapped = Arc::new(AtomicUsize::new(0));
+        let counter = mapped.clone();
+
+        let mut endless = lake_map_ordered(0u64.., move |x| { counter.fetch_add(1, Ordering::SeqCst); x + 1 }).threads(2).batch_size(4);
+        assert_eq!(endless.next(), Some(1));
+        assert_eq!(endless.next(), Some(2));
+        drop(endless);
+
+        //The mapping closure is only dropped once every thread in the lake has finished
+        while Arc::strong_count(&mapped) > 1 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        //Two threads give a window of four batches of four items
+        assert!(mapped.load(Ordering::SeqCst) <= 16);
+    }
+
+    #[test]
+    fn first_and_last() {
+        let mut data: Vec<u64> = vec![0; 1_000_000];
+        for index in [3_000, 759_246, 759_247, 999_000] {
+            data[index] = index as u64;
+        }
+
+        //The data is shared with the lake rather than consumed, so the same data can be searched again
+        let data: Arc<[u64]> = data.into();
+
+        assert_eq!(lake_position(10, &data, |x| *x != 0), Some(3_000));
+        assert_eq!(lake_position(10, &data, |x| *x > 700_000), Some(759_246));
+        assert_eq!(lake_position(10, &data, |x| *x == 1), None);
+        assert_eq!(lake_find_first(4, &data, |x| *x > 5_000), Some(&759_246));
+        assert_eq!(lake_find_last(4, &data, |x| *x != 0 && *x < 999_000), Some(&759_247));
+        assert_eq!(lake_find_last(4, &data, |x| *x == 1), None);
+        assert_eq!(lake_find_first(4, &Arc::from(Vec::<u64>::new()), |_| true), None);
+        assert_eq!(data.len(), 1_000_000);
+    }
+
+    #[test]
+    fn parallel_sort() {
+        //A simple pseudo-random sequence, so the test needs no dependencies
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        let numbers: Vec<u64> = (0..100_003).map(|_| { seed ^= seed << 13; seed ^= seed >> 7; seed ^= seed << 17; seed % 1000 }).collect();
+
+        let mut expected = numbers.clone();
+        expected.sort();
+
+        for threads in [1, 2, 3, 7, 8] {
+            let mut sorted = numbers.clone();
+            lake_sort(threads, &mut sorted);
+            assert_eq!(sorted, expected);
+
+            let mut unstable = numbers.clone();
+            lake_sort_unstable(threads, &mut unstable);
+            assert_eq!(unstable, expected);
+        }
+
+        //Strings are not Copy, and the stable sorts keep equal keys in their original order
+        let mut words: Vec<(u64, String)> = numbers.iter().enumerate().map(|(index, x)| (x % 10, index.to_string())).collect();
+        let mut expected = words.clone();
+        expected.sort_by_key(|(key, _)| *key);
+
+        lake_sort_by_key(5, &mut words, |(key, _)| *key);
+        assert_eq!(words, expected);
+
+        lake_sort_by(6, &mut words, |a, b| b.0.cmp(&a.0));
+        expected.sort_by_key(|(key, _)| std::cmp::Reverse(*key));
+        assert_eq!(words, expected);
+
+        let mut tiny = vec![String::from("b"), String::from("a")];
+        lake_sort(4, &mut tiny);
+        assert_eq!(tiny, ["a", "b"]);
+
+        //A panicking comparison is passed on, leaving the vector empty
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            lake_sort_by(4, &mut words, |_, _| panic!("This panic is deliberate, used to test that sorts pass on panics"));
+        }));
+
+        assert!(result.is_err());
+        assert!(words.is_empty());
+    }
+
+    #[test]
+    fn prefix_scans() {
+        let numbers: Vec<u64> = (1..=10_001).collect();
+
+        let mut inclusive = numbers.clone();
+        lake_scan_inclusive(4, &mut inclusive, |a, b| a + b);
+        assert_eq!(inclusive, numbers.iter().map(|x| x * (x + 1) / 2).collect::<Vec<_>>());
+
+        let mut exclusive = numbers.clone();
+        lake_scan_exclusive(3, &mut exclusive, 0, |a, b| a + b);
+        assert_eq!(exclusive, numbers.iter().map(|x| x * (x - 1) / 2).collect::<Vec<_>>());
+
+        //Concatenation is associative but not commutative, so this checks the pieces are combined in order
+        let mut words: Vec<String> = ["a", "b", "c", "d", "e"].iter().map(|x| x.to_string()).collect();
+        lake_scan_inclusive(8, &mut words, |a, b| format!("{}{}", a, b));
+        assert_eq!(words, ["a", "ab", "abc", "abcd", "abcde"]);
+
+        let mut words: Vec<String> = ["a", "b", "c", "d", "e"].iter().map(|x| x.to_string()).collect();
+        lake_scan_exclusive(2, &mut words, String::new(), |a, b| format!("{}{}", a, b));
+        assert_eq!(words, ["", "a", "ab", "abc", "abcd"]);
+
+        //Threads can exchange values directly
+        let lake = Builder::new(4).spawn(|x: ThreadUtilities<()>| x.all_gather(x.index() * 10));
+        for gathered in lake.join_iter() {
+            assert_eq!(gathered.unwrap(), [0, 10, 20, 30]);
+        }
+    }
+
+    #[test]
+    fn prefix_scans_in_lake() {
+        let words: Vec<String> = ["a", "b", "c"].iter().map(|x| x.to_string()).collect();
+
+        //More threads than elements, so some threads have nothing to scan
+        let lake = Builder::with_data(5, (Disjointer::new(words.clone()), Disjointer::new(words)))
+            .spawn(|x: ThreadUtilities<(Disjointer<String>, Disjointer<String>)>| {
+                let (inclusive, exclusive) = x.data();
+                x.scan_inclusive(inclusive, |a, b| format!("{}{}", a, b));
+                x.scan_exclusive(exclusive, String::new(), |a, b| format!("{}{}", a, b));
+            });
+
+        let (inclusive, exclusive) = lake.join().unwrap();
+
+        assert_eq!(inclusive.take(), ["a", "ab", "abc"]);
+        assert_eq!(exclusive.take(), ["", "a", "ab"]);
+
+        //A panic in the middle of a scan loses the vector
+        let mut numbers: Vec<u64> = (0..1000).collect();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            lake_scan_inclusive(4, &mut numbers, |a, b| if *b == 500 { panic!("This panic is deliberate, used to test that a failed scan empties the vector") } else { a + b });
+        }));
+
+        assert!(result.is_err());
+        assert!(numbers.is_empty());
+    }
+
+    #[test]
+    fn filter_and_partition() {
+        let numbers: Vec<u64> = (0..10_007).collect();
+
+        let multiples = lake_filter_collect(4, numbers.clone(), |x| x % 3 == 0);
+        assert_eq!(multiples, numbers.iter().copied().filter(|x| x % 3 == 0).collect::<Vec<_>>());
+
+        //Elements that are not Copy are moved, and the rejected ones are dropped exactly once
+        let counter = Arc::new(());
+        let tracked: Vec<(u64, Arc<()>)> = numbers.iter().map(|x| (*x, counter.clone())).collect();
+
+        let (even, odd) = lake_partition(3, tracked, |(x, _)| x % 2 == 0);
+        assert_eq!(even.iter().map(|(x, _)| *x).collect::<Vec<_>>(), numbers.iter().copied().filter(|x| x % 2 == 0).collect::<Vec<_>>());
+        assert_eq!(odd.iter().map(|(x, _)| *x).collect::<Vec<_>>(), numbers.iter().copied().filter(|x| x % 2 == 1).collect::<Vec<_>>());
+
+        let large = lake_filter_collect(5, odd, |(x, _)| *x > 10_000);
+        assert_eq!(large.len(), 3);
+        drop(even);
+        assert_eq!(Arc::strong_count(&counter), 4);
+
+        assert!(lake_filter_collect(4, Vec::<u64>::new(), |_| true).is_empty());
+        assert_eq!(lake_partition(8, vec![1, 2], |x| *x > 5), (vec![], vec![1, 2]));
+    }
+
+    #[test]
+    fn aggregations() {
+        let numbers: Vec<u64> = (0..10_000).collect();
+
+        let histogram = lake_histogram(6, numbers.clone(), |x| (x % 10) as usize, 12);
+        assert_eq!(histogram, [1000, 1000, 1000, 1000, 1000, 1000, 1000, 1000, 1000, 1000, 0, 0]);
+        assert_eq!(lake_histogram(4, Vec::<u64>::new(), |_| 0, 3), [0, 0, 0]);
+
+        let sparse = lake_histogram_sparse(5, numbers.clone(), |x| x / 2500);
+        assert_eq!(sparse.len(), 4);
+        assert!(sparse.values().all(|count| *count == 2500));
+
+        //Values are folded in order, so concatenating them gives the digits in order
+        let groups = lake_group_by(7, numbers, |x| (x % 3, x.to_string()), |a, b| a + "," + &b);
+        let expected: Vec<String> = (0..10_000u64).filter(|x| x % 3 == 1).map(|x| x.to_string()).collect();
+        assert_eq!(groups[&1], expected.join(","));
+
+        //Threads can combine values in a tree themselves
+        let lake = Builder::new(5).spawn(|x: ThreadUtilities<()>| x.reduce(vec![x.index()], |mut a, b| { a.extend(b); a }));
+        let reduced: Vec<_> = lake.join_iter().map(|result| result.unwrap()).collect();
+        assert_eq!(reduced, [Some(vec![0, 1, 2, 3, 4]), None, None, None, None]);
+    }
+
+    #[test]
+    fn deterministic_sums() {
+        let values: Vec<f64> = (0..100_000u64).map(|x| ((x * 7919) % 1000) as f64 * 0.1 + if x % 3 == 0 { 1e10 } else { 1e-3 }).collect();
+
+        for summation in [Summation::Naive, Summation::Compensated] {
+            let one = lake_sum_deterministic(1, values.clone(), summation);
+
+            for threads in [2, 4, 7, 64] {
+                assert_eq!(lake_sum_deterministic(threads, values.clone(), summation).to_bits(), one.to_bits());
+            }
+        }
+
+        //Compensation keeps the small values that plain addition loses next to the large ones
+        let cancelling: Vec<f64> = (0..10_000).map(|x| [1.0, 1e100, 1.0, -1e100][x % 4]).collect();
+        assert_eq!(lake_sum_deterministic(4, cancelling.clone(), Summation::Compensated), 5000.0);
+        assert_ne!(lake_sum_deterministic(4, cancelling, Summation::Naive), 5000.0);
+        assert_eq!(lake_sum_deterministic(4, Vec::<f32>::new(), Summation::Naive), 0.0);
+
+        let words: Vec<String> = (0..5000).map(|x| (x % 10).to_string()).collect();
+        assert_eq!(lake_reduce_deterministic(3, words.clone(), |a, b| format!("{}{}", a, b)), Some(words.concat()));
+        assert_eq!(lake_reduce_deterministic(3, Vec::<String>::new(), |a, b| format!("{}{}", a, b)), None);
+    }
+
+    #[test]
+    fn extrema() {
+        let numbers: Vec<u64> = (0..10_000u64).map(|x| (x * 7919) % 1000).collect();
+
+        assert_eq!(lake_top_k(4, numbers.clone(), 3, |a, b| a.cmp(b)), [999, 999, 999]);
+        assert_eq!(lake_argmax(4, numbers.clone()), numbers.iter().position(|x| *x == 999));
+        assert_eq!(lake_argmin(3, numbers.clone()), Some(0));
+        assert_eq!(lake_argmin(3, Vec::<u64>::new()), None);
+
+        //Elements with equal keys come back in the order they appear in the data
+        let tagged: Vec<(u64, String)> = numbers.iter().enumerate().map(|(index, x)| (*x, index.to_string())).collect();
+        let first_max = numbers.iter().position(|x| *x == 999).unwrap();
+
+        let top = lake_top_k(6, tagged.clone(), 4, |a, b| a.0.cmp(&b.0));
+        let expected: Vec<String> = numbers.iter().enumerate().filter(|(_, x)| **x == 999).take(4).map(|(index, _)| index.to_string()).collect();
+        assert_eq!(top.into_iter().map(|(_, tag)| tag).collect::<Vec<_>>(), expected);
+
+        assert_eq!(lake_max_by_key(5, tagged.clone(), |(x, _)| *x), Some((999, first_max.to_string())));
+        assert_eq!(lake_min_by_key(5, tagged, |(x, _)| *x), Some((0, String::from("0"))));
+        assert!(lake_top_k(2, vec![1, 2, 3], 0, |a: &u64, b| a.cmp(b)).is_empty());
+        assert_eq!(lake_top_k(8, vec![1, 3, 2], 5, |a: &u64, b| a.cmp(b)), [3, 2, 1]);
+
+        //Asking for far more elements than there are only reserves room for the elements each thread has
+        assert_eq!(lake_top_k(2, vec![1, 3, 2], usize::MAX, |a: &u64, b| a.cmp(b)), [3, 2, 1]);
+        assert_eq!(lake_top_k(2, vec![1, 3, 2], 1 << 40, |a: &u64, b| a.cmp(b)), [3, 2, 1]);
+    }
+
+    #[test]
+    fn unique() {
+        let numbers: Vec<u64> = (0..10_000u64).map(|x| (x * 7919) % 1000).collect();
+
+        let mut expected = Vec::new();
+        for x in &numbers {
+            if !expected.contains(x) {
+                expected.push(*x);
+            }
+        }
+
+        assert_eq!(lake_unique(4, numbers.clone(), UniqueOrder::FirstOccurrence), expected);
+
+        let mut unordered = lake_unique(5, numbers.clone(), UniqueOrder::Unordered);
+        unordered.sort_unstable();
+        assert_eq!(unordered, (0..1000).collect::<Vec<_>>());
+
+        //The first element with each key is the one kept
+        let tagged: Vec<(u64, String)> = numbers.iter().enumerate().map(|(index, x)| (*x % 10, index.to_string())).collect();
+        let deduped = lake_dedup_by_key(3, tagged.clone(), |(key, _)| *key, UniqueOrder::FirstOccurrence);
+        assert_eq!(deduped, tagged[..10].to_vec());
+
+        let mut deduped = lake_dedup_by_key(6, tagged.clone(), |(key, _)| *key, UniqueOrder::Unordered);
+        deduped.sort();
+        let mut expected = tagged[..10].to_vec();
+        expected.sort();
+        assert_eq!(deduped, expected);
+
+        assert!(lake_unique(4, Vec::<String>::new(), UniqueOrder::FirstOccurrence).is_empty());
+        assert_eq!(lake_unique(8, vec![String::from("a"), String::from("a")], UniqueOrder::Unordered), ["a"]);
+    }
+
+    #[test]
+    fn breadth_first_search() {
+        //A 40 by 40 grid, with every vertex joined to its neighbours above, below, left and right
+        let side = 40;
+        let neighbours = move |vertex: usize| {
+            let mut neighbours = Vec::new();
+            if !vertex.is_multiple_of(side) { neighbours.push(vertex - 1) }
+            if vertex % side < side - 1 { neighbours.push(vertex + 1) }
+            if vertex >= side { neighbours.push(vertex - side) }
+            if vertex < side * (side - 1) { neighbours.push(vertex + side) }
+            neighbours
+        };
+
+        let depths: Vec<AtomicUsize> = (0..side * side).map(|_| AtomicUsize::new(0)).collect();
+
+        let lake = Builder::with_data(4, depths)
+            .frontier(vec![0usize], side * side)
+            .spawn(move |x: ThreadUtilities<Vec<AtomicUsize>>| {
+                let frontier = x.frontier::<usize>();
+
+                if x.barrier() {
+                    frontier.visit(0);
+                }
+                x.barrier();
+
+                frontier.expand(&x, |vertex, next| {
+                    for neighbour in neighbours(*vertex) {
+                        if frontier.visit(neighbour) {
+                            x.data()[neighbour].store(frontier.round() + 1, Ordering::Relaxed);
+                            next.push(neighbour);
+                        }
+                    }
+                })
+            });
+
+        let frontier = lake.frontier::<usize>();
+        let depths = lake.arc();
+
+        //The far corner is found last, and the round after finds nothing
+        assert!(lake.join_all().into_iter().all(|rounds| rounds == 2 * (side - 1) + 1));
+        assert!((0..side * side).all(|vertex| frontier.visited(vertex)));
+        assert!((0..side * side).all(|vertex| depths[vertex].load(Ordering::Relaxed) == vertex % side + vertex / side));
+    }
+
+    #[test]
+    fn jacobi_iteration() {
+        //Every point moves to the average of its neighbours, with the ends held at 0 and 1, which converges to a straight line
+        let points = 21;
+        let step = move |index: usize, previous: &[f64]| {
+            if index == 0 || index == points - 1 {
+                return (previous[index], 0.0)
+            }
+
+            let value = (previous[index - 1] + previous[index + 1]) / 2.0;
+            (value, (value - previous[index]).abs())
+        };
+
+        let mut start = vec![0.0; points];
+        start[points - 1] = 1.0;
+
+        let result = lake_iterate(4, start.clone(), step, |residual| residual < 1e-9, 100_000).join();
+
+        assert!(result.converged());
+        assert!(result.residual() < 1e-9);
+        assert!(result.state().iter().enumerate().all(|(index, value)| (value - index as f64 / (points - 1) as f64).abs() < 1e-6));
+
+        //The iteration count does not depend on the number of threads
+        let serial = lake_iterate(1, start.clone(), step, |residual| residual < 1e-9, 100_000).join();
+        assert_eq!(serial.iterations(), result.iterations());
+        assert_eq!(serial.into_state(), result.into_state());
+
+        let capped = lake_iterate(3, start.clone(), step, |_| false, 7).join();
+        assert!(!capped.converged());
+        assert_eq!(capped.iterations(), 7);
+
+        //Stopping does not wait for convergence
+        let stopped = lake_iterate(2, start, step, |_| false, usize::MAX);
+        std::thread::sleep(Duration::from_millis(50));
+        stopped.stop();
+        assert!(!stopped.join().converged());
+    }
+
+    #[test]
+    fn reproducible_rng() {
+        //Estimate pi from 64 blocks of random points, with each block's stream keyed by the block rather than the thread
+        let estimate = |threads: usize, seed: u64| {
+            let lake = Builder::new(threads)
+                .seed(seed)
+                .spawn(|x: ThreadUtilities<_>| {
+                    x.range(64).map(|block| {
+                        let mut rng = x.rng(block as u64);
+                        (0..1000).filter(|_| {
+                            let (a, b) = (rng.next_f64(), rng.next_f64());
+                            a * a + b * b < 1.0
+                        }).count()
+                    }).sum::<usize>()
+                });
+
+            4.0 * lake.join_all().into_iter().sum::<usize>() as f64 / 64_000.0
+        };
+
+        let pi = estimate(1, 42);
+        assert!((pi - std::f64::consts::PI).abs() < 0.05);
+        assert_eq!(estimate(3, 42), pi);
+        assert_eq!(estimate(8, 42), pi);
+        assert_ne!(estimate(4, 43), pi);
+
+        let mut rng = Rng::new(7, 0);
+        assert!((0..1000).all(|_| rng.below(10) < 10));
+        assert_ne!(Rng::new(7, 0).next_u64(), Rng::new(7, 1).next_u64());
+    }
+
+    #[test]
+    fn balanced_split() {
+        //10 elements over 4 threads gives pieces of 3, 3, 2 and 2, rather than 2, 2, 2 and 4
+        let lake = Builder::with_data(4, Disjointer::new(vec![0usize; 10]))
+            .spawn(|x: ThreadUtilities<Disjointer<usize>>| {
+                let mut piece = x.data().balanced_piece(&x);
+
+                assert_eq!(piece.offset(), x.balanced_range(10).start);
+                for (index, element) in piece.enumerate_global() {
+                    *element = index;
+                }
+
+                let numbers: Vec<usize> = (0..10).collect();
+                let slice = x.balanced_split_slice(&numbers);
+                assert!(slice.enumerate_global().all(|(index, element)| *element == index));
+                assert_eq!(slice.global_index(0), slice.offset());
+
+                piece.len()
+            });
+
+        let data = lake.arc();
+        assert_eq!(lake.join_all(), [3, 3, 2, 2]);
+        assert_eq!(Arc::try_unwrap(data).ok().unwrap().take(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn split_ranges() {
+        let lake = Builder::new(3)
+            .spawn(|x: ThreadUtilities<_>| {
+                (x.split_range(-10i32..10), x.split_range(0..=u64::MAX), x.split_range(i8::MIN..=i8::MAX),
+                 x.split_range(0..=u128::MAX), x.split_range(5u8..5), x.split_range(0..2usize),
+                 x.split_range_step(-50i64..50, 7).map(|values| values.collect::<Vec<_>>()))
+            });
+
+        let pieces = lake.join_all();
+
+        let values: Vec<i32> = pieces.iter().flat_map(|piece| piece.0.clone().unwrap()).collect();
+        assert_eq!(values, (-10..10).collect::<Vec<_>>());
+
+        //Whole-type ranges are split into adjacent pieces with nothing lost at either end
+        let full: Vec<_> = pieces.iter().map(|piece| piece.1.clone().unwrap()).collect();
+        assert_eq!(*full[0].start(), 0);
+        assert_eq!(*full[2].end(), u64::MAX);
+        assert!(full.windows(2).all(|pair| *pair[0].end() + 1 == *pair[1].start()));
+        assert_eq!(*full[0].end() - *full[0].start(), u64::MAX / 3);
+
+        let bytes: Vec<i8> = pieces.iter().flat_map(|piece| piece.2.clone().unwrap()).collect();
+        assert_eq!(bytes, (i8::MIN..=i8::MAX).collect::<Vec<_>>());
+
+        let wide: Vec<_> = pieces.iter().map(|piece| piece.3.clone().unwrap()).collect();
+        assert_eq!((*wide[0].start(), *wide[2].end()), (0, u128::MAX));
+        assert!(wide.windows(2).all(|pair| *pair[0].end() + 1 == *pair[1].start()));
+
+        assert!(pieces.iter().all(|piece| piece.4.is_none()));
+        assert_eq!(pieces.iter().map(|piece| piece.5.clone()).collect::<Vec<_>>(), [Some(0..=0), Some(1..=1), None]);
+
+        let stepped: Vec<i64> = pieces.iter().flat_map(|piece| piece.6.clone().unwrap()).collect();
+        assert_eq!(stepped, (-50..50).step_by(7).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn router_early_exit() {
+        //Thread 0 stops after its first message, which must not leave thread 1 waiting for it forever
+        let lake = Builder::new(2)
+            .router::<usize>()
+            .spawn(|x: ThreadUtilities<()>| {
+                for i in 0..20usize {
+                    x.route(&i, i);
+                }
+
+                let mut handled = 0;
+                while x.next_routed::<usize>().is_some() {
+                    handled += 1;
+
+                    if x.index() == 0 {
+                        break
+                    }
+                }
+
+                handled
+            });
+
+        lake.close_router::<usize>();
+
+        let handled = lake.join_all();
+        assert_eq!(handled[0], 1);
+        assert!(handled[1] > 0);
+    }
+
+    #[test]
+    fn barrier_leader_after_leave() {
+        //Thread 2 returns while the others are waiting, which opens the barrier, and one of the waiters must still lead
+        let leaders = Arc::new(AtomicUsize::new(0));
+
+        let lake = Builder::with_data(3, leaders.clone())
+            .spawn(|x: ThreadUtilities<Arc<AtomicUsize>>| {
+                if x.index() == 2 {
+                    std::thread::sleep(Duration::from_millis(100));
+                    return
+                }
+
+                for _ in 0..3 {
+                    if x.barrier() {
+                        x.data().fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+
+        lake.join_all();
+
+        assert_eq!(leaders.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn zero_thread_count() {
+        //A thread count that comes out as zero still runs on one thread rather than skipping the work
+        let none = |_: Option<usize>| 0;
+
+        let mut numbers = vec![3, 1, 2];
+        lake_sort(none, &mut numbers);
+        assert_eq!(numbers, [1, 2, 3]);
+
+        assert_eq!(lake_filter_collect(none, vec![1, 2, 3], |_| true), [1, 2, 3]);
+        assert_eq!(lake_partition(none, vec![1, 2, 3], |x| *x != 2), (vec![1, 3], vec![2]));
+
+        assert_eq!(lake_unique(none, vec![1, 1, 2], UniqueOrder::FirstOccurrence), [1, 2]);
+
+        let mut numbers = vec![1, 2, 3];
+        lake_scan_inclusive(none, &mut numbers, |a, b| a + b);
+        assert_eq!(numbers, [1, 3, 6]);
+
+        assert_eq!(lake_position(none, &Arc::from(vec![1, 2, 3]), |x| *x == 2), Some(1));
+
+        assert_eq!(lake_histogram(none, vec![0, 1, 1], |x| *x, 2), [1, 2]);
+
+        assert_eq!(lake_sum_deterministic(none, vec![1.0, 2.0, 3.0], Summation::Naive), 6.0);
+
+        let counts = map_reduce(none, vec![1, 1, 2], |x: &u64, out: &mut Emitter<'_, u64, usize>| out.emit(*x, 1), |a, b| a + b);
+        assert_eq!(counts[&1], 2);
+
+        assert_eq!(lake_argmin(none, vec![3, 1, 2]), Some(1));
+    }
 }
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+type Map<T, K, V> = Box<dyn Fn(&T, &mut Emitter<'_, K, V>) + Send + Sync>;
+
+type Fold<V> = Box<dyn Fn(V, V) -> V + Send + Sync>;
+
+///Collects the `(key, value)` pairs a map function emits for one element
+pub struct Emitter<'a, K, V> {
+    _sink: & 'a mut dyn FnMut(K, V),
+}
+
+impl<'a, K, V> Emitter<'a, K, V> {
+
+    ///Emit a pair, to be reduced with every other value emitted for the same key
+    pub fn emit(&mut self, key: K, value: V) {
+        (self._sink)(key, value)
+    }
+}
+
+///A map/reduce job, run on the threads of a lake
+///
+/// Each thread maps its share of the input, from [`ThreadUtilities::split_slice`], into `(key, value)` pairs. The pairs are
+/// shuffled to the thread that owns their key, which reduces the values for each of its keys. The reduce function is
+/// applied in no particular order, so it should be associative and commutative.
+pub struct MapReduce<T, K, V> {
+    _map: Map<T, K, V>,
+    _reduce: Fold<V>,
+    _combine: Option<Fold<V>>,
+}
+
+impl<T, K, V> MapReduce<T, K, V>
+    where T: Send + Sync + 'static, K: Hash + Eq + Send + 'static, V: Send + 'static
+{
+    ///Create a job from a map function, which emits pairs for each element, and a reduce function, which merges two values for the same key
+    pub fn new<F, R>(map: F, reduce: R) -> Self
+        where F: Fn(&T, &mut Emitter<'_, K, V>) + Send + Sync + 'static, R: Fn(V, V) -> V + Send + Sync + 'static
+    {
+        Self { _map: Box::new(map), _reduce: Box::new(reduce), _combine: None }
+    }
+
+    ///Merge values for the same key on the mapping thread before they are shuffled, so fewer pairs are sent between threads
+    ///
+    /// Often this is the same function as the reducer
+    pub fn combiner<F>(mut self, combine: F) -> Self
+        where F: Fn(V, V) -> V + Send + Sync + 'static
+    {
+        self._combine = Some(Box::new(combine));
+        self
+    }
+
+    ///Run the job over `data` on a lake, returning the reduced value for every key
+    pub fn run<C: ThreadCount>(self, thread_count: C, data: Vec<T>) -> HashMap<K, V> {
+        let threads = threads_for(thread_count, data.len());
+
+        let lake = Builder::with_data(threads, (data, self))
+            .router::<(K, V)>()
+            .spawn(|x: ThreadUtilities<(Vec<T>, MapReduce<T, K, V>)>| {
+                let (data, job) = x.data();
+
+                match &job._combine {
+                    Some(combine) => {
+                        let mut combined = HashMap::new();
+
+                        for element in x.split_slice(data) {
+                            (job._map)(element, &mut Emitter { _sink: &mut |key, value| merge(&mut combined, key, value, combine) });
+                        }
+
+                        for (key, value) in combined {
+                            shuffle(&x, key, value);
+                        }
+                    }
+                    None => {
+                        for element in x.split_slice(data) {
+                            (job._map)(element, &mut Emitter { _sink: &mut |key, value| shuffle(&x, key, value) });
+                        }
+                    }
+                }
+
+                //Once every thread is past the barrier, every pair is in its owner's inbox
+                x.barrier();
+
+                let mut shard = HashMap::new();
+
+                while let Some((key, value)) = x.try_routed::<(K, V)>() {
+                    merge(&mut shard, key, value, &job._reduce);
+                }
+
+                shard
+            });
+
+        let mut result = HashMap::new();
+
+        //Each key is owned by exactly one thread, so the shards never overlap
+        for shard in lake.join_iter() {
+            result.extend(shard.expect("a map or reduce function panicked"));
+        }
+
+        result
+    }
+}
+
+///Send a pair to the thread that owns its key
+fn shuffle<D, K: Hash + Send + 'static, V: Send + 'static>(x: &ThreadUtilities<D>, key: K, value: V) {
+    x.route_to(x.owner::<K, (K, V)>(&key), (key, value));
+}
+
+fn merge<K: Hash + Eq, V>(map: &mut HashMap<K, V>, key: K, value: V, fold: &Fold<V>) {
+    let value = match map.remove(&key) {
+        Some(previous) => fold(previous, value),
+        None => value,
+    };
+
+    map.insert(key, value);
+}
+
+///Run a map/reduce job over `data` on a lake with `thread_count` threads, see [`MapReduce`]
+pub fn map_reduce<C, T, K, V, F, R>(thread_count: C, data: Vec<T>, map: F, reduce: R) -> HashMap<K, V>
+    where C: ThreadCount, T: Send + Sync + 'static, K: Hash + Eq + Send + 'static, V: Send + 'static,
+          F: Fn(&T, &mut Emitter<'_, K, V>) + Send + Sync + 'static, R: Fn(V, V) -> V + Send + Sync + 'static
+{
+    MapReduce::new(map, reduce).run(thread_count, data)
+}
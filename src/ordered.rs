@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::thread::available_parallelism;
+use crate::builder::Builder;
+use crate::threadlake::ThreadLake;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, FullParallelism};
+
+///A batch of input items, numbered in the order they were taken from the iterator
+struct Batch<T>(usize, Vec<T>);
+
+///The results for a batch, or the panic raised while mapping it
+struct Mapped<U>(usize, std::thread::Result<Vec<U>>);
+
+///Maps the items of an iterator on a lake, yielding the results in the same order as the input
+///
+/// Created with [`lake_map_ordered`]. Items are taken from the iterator in batches, on the thread calling
+/// [`Iterator::next`], and handed to the threads of a lake. At most `window` batches are in flight at once, counting
+/// those mapped but waiting for an earlier batch to finish, which bounds the memory used to put results back in order.
+/// The lake starts with the first call to `next`, and is stopped when the iterator is dropped.
+pub struct OrderedMap<I: Iterator, U, F: Send + Sync + 'static> {
+    _input: I,
+    _f: Option<F>,
+    _lake: Option<ThreadLake<F, ()>>,
+    _threads: usize,
+    _batch_size: usize,
+    _window: Option<usize>,
+    _next_batch: usize, //Number of the next batch to submit
+    _expected: usize, //Number of the next batch to yield
+    _in_flight: usize,
+    _exhausted: bool,
+    _reorder: BTreeMap<usize, Vec<U>>,
+    _current: std::vec::IntoIter<U>,
+}
+
+impl<I, U, F> OrderedMap<I, U, F>
+    where I: Iterator, I::Item: Send + 'static, U: Send + 'static, F: Fn(I::Item) -> U + Send + Sync + 'static
+{
+    ///Set the number of threads in the lake. Defaults to [`FullParallelism`]. A count of zero runs on one thread
+    pub fn threads<C: ThreadCount>(mut self, thread_count: C) -> Self {
+        self._threads = thread_count.get(available_parallelism().map(|x| x.get())).max(1);
+        self
+    }
+
+    ///Set how many items are handed to a thread at once. Defaults to 64
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batches need at least one item");
+        self._batch_size = batch_size;
+        self
+    }
+
+    ///Set the most batches that can be in flight at once. Defaults to twice the number of threads
+    pub fn window(mut self, window: usize) -> Self {
+        assert!(window > 0, "the window needs room for at least one batch");
+        self._window = Some(window);
+        self
+    }
+
+    fn start(&mut self) {
+        let f = self._f.take().unwrap();
+
+        let lake = Builder::with_data(self._threads, f)
+            .queue::<Batch<I::Item>>()
+            .channel::<Mapped<U>>()
+            .spawn(|x: ThreadUtilities<F>| {
+                while let Some(Batch(number, items)) = x.next_item::<Batch<I::Item>>() {
+                    let mapped = catch_unwind(AssertUnwindSafe(|| items.into_iter().map(x.data()).collect()));
+
+                    if x.post(Mapped(number, mapped)).is_err() {
+                        break
+                    }
+                }
+            });
+
+        self._lake = Some(lake);
+    }
+
+    ///Submit batches until the window is full or the input runs out
+    fn fill(&mut self) {
+        let window = *self._window.get_or_insert(self._threads * 2);
+
+        while !self._exhausted && self._in_flight < window {
+            let batch: Vec<_> = self._input.by_ref().take(self._batch_size).collect();
+
+            if batch.len() < self._batch_size {
+                self._exhausted = true;
+            }
+
+            if batch.is_empty() {
+                break
+            }
+
+            if self._lake.is_none() {
+                self.start();
+            }
+
+            if self._lake.as_ref().unwrap().submit(Batch(self._next_batch, batch)).is_err() {
+                unreachable!("the queue is only closed once the input runs out");
+            }
+
+            self._next_batch += 1;
+            self._in_flight += 1;
+        }
+
+        if self._exhausted {
+            if let Some(lake) = &self._lake {
+                lake.close_queue::<Batch<I::Item>>();
+            }
+        }
+    }
+}
+
+impl<I, U, F> Iterator for OrderedMap<I, U, F>
+    where I: Iterator, I::Item: Send + 'static, U: Send + 'static, F: Fn(I::Item) -> U + Send + Sync + 'static
+{
+    type Item = U;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self._current.next() {
+                return Some(item)
+            }
+
+            self.fill();
+
+            if self._in_flight == 0 {
+                return None
+            }
+
+            while !self._reorder.contains_key(&self._expected) {
+                let lake = self._lake.as_ref().unwrap();
+
+                match lake.channel::<Mapped<U>>().unwrap().recv() {
+                    Ok(Mapped(number, Ok(items))) => { self._reorder.insert(number, items); }
+                    Ok(Mapped(_, Err(payload))) => resume_unwind(payload),
+                    Err(_) => panic!("the lake finished before mapping every batch"),
+                }
+            }
+
+            self._current = self._reorder.remove(&self._expected).unwrap().into_iter();
+            self._expected += 1;
+            self._in_flight -= 1;
+        }
+    }
+}
+
+impl<I: Iterator, U, F: Send + Sync + 'static> Drop for OrderedMap<I, U, F> {
+    fn drop(&mut self) {
+        //Threads still mapping finish their batch, then find the lake stopped
+        if let Some(lake) = &self._lake {
+            lake.stop();
+        }
+    }
+}
+
+///Map the items of `input` with `f` on a lake, yielding the results in input order, see [`OrderedMap`]
+///
+/// Suits iterators whose length is not known up front, such as lines read from a file
+pub fn lake_map_ordered<I, U, F>(input: I, f: F) -> OrderedMap<I::IntoIter, U, F>
+    where I: IntoIterator, I::Item: Send + 'static, U: Send + 'static, F: Fn(I::Item) -> U + Send + Sync + 'static
+{
+    OrderedMap {
+        _input: input.into_iter(),
+        _f: Some(f),
+        _lake: None,
+        _threads: 0,
+        _batch_size: 64,
+        _window: None,
+        _next_batch: 0,
+        _expected: 0,
+        _in_flight: 0,
+        _exhausted: false,
+        _reorder: BTreeMap::new(),
+        _current: Vec::new().into_iter(),
+    }.threads(FullParallelism)
+}
@@ -0,0 +1,205 @@
+use std::iter::Sum;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::available_parallelism;
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, FullParallelism};
+
+///One chunk of the items for each thread, taken by the thread with the same index
+type Chunks<T> = Vec<Mutex<Option<Vec<T>>>>;
+
+///Items to be processed in parallel on a lake, created with [`LakeIterExt::into_lake`]
+///
+/// Every operation splits the items into one contiguous chunk per thread, and puts the results back together in
+/// order, so the output is in the same order as the input. Operations returning a [`LakeIter`] run straight away, and
+/// the next operation starts from their results.
+pub struct LakeIter<T> {
+    _items: Vec<T>,
+    _threads: usize,
+}
+
+impl<T: Send + 'static> LakeIter<T> {
+
+    ///Set the number of threads used by the following operations. Defaults to [`FullParallelism`]
+    pub fn threads<C: ThreadCount>(mut self, thread_count: C) -> Self {
+        self._threads = thread_count.get(available_parallelism().map(|x| x.get()));
+        self
+    }
+
+    ///Call `f` on every item, keeping the results in order
+    pub fn lake_map<U, F>(self, f: F) -> LakeIter<U>
+        where U: Send + 'static, F: Fn(T) -> U + Send + Sync + 'static
+    {
+        let threads = self._threads;
+        let chunks = self.run(move |chunk| chunk.into_iter().map(&f).collect::<Vec<_>>());
+
+        LakeIter { _items: chunks.into_iter().flatten().collect(), _threads: threads }
+    }
+
+    ///Keep the items for which `f` returns true, in order
+    pub fn lake_filter<F>(self, f: F) -> LakeIter<T>
+        where F: Fn(&T) -> bool + Send + Sync + 'static
+    {
+        let threads = self._threads;
+        let chunks = self.run(move |chunk| chunk.into_iter().filter(&f).collect::<Vec<_>>());
+
+        LakeIter { _items: chunks.into_iter().flatten().collect(), _threads: threads }
+    }
+
+    ///Call `f` on every item
+    pub fn lake_for_each<F>(self, f: F)
+        where F: Fn(T) + Send + Sync + 'static
+    {
+        self.run(move |chunk| chunk.into_iter().for_each(&f));
+    }
+
+    ///Combine the items with `f`, in order, so `f` only needs to be associative. Returns `None` if there are no items
+    pub fn lake_reduce<F>(self, f: F) -> Option<T>
+        where F: Fn(T, T) -> T + Send + Sync + 'static
+    {
+        let f = Arc::new(f);
+        let reduce = f.clone();
+
+        self.run(move |chunk| chunk.into_iter().reduce(&*reduce)).into_iter().flatten().reduce(&*f)
+    }
+
+    ///Add up the items
+    pub fn lake_sum<S>(self) -> S
+        where S: Sum<T> + Sum<S> + Send + 'static
+    {
+        self.run(|chunk| chunk.into_iter().sum::<S>()).into_iter().sum()
+    }
+
+    ///Returns true if `f` returns true for any item. Threads stop early once any of them has found one
+    pub fn lake_any<F>(self, f: F) -> bool
+        where F: Fn(T) -> bool + Send + Sync + 'static
+    {
+        let found = Arc::new(AtomicBool::new(false));
+        let shared = found.clone();
+
+        self.run(move |chunk| {
+            if chunk.into_iter().any(|item| shared.load(Ordering::Relaxed) || f(item)) {
+                shared.store(true, Ordering::Relaxed);
+            }
+        });
+
+        found.load(Ordering::Relaxed)
+    }
+
+    ///Returns true if `f` returns true for every item. Threads stop early once any of them has found an item where it does not
+    pub fn lake_all<F>(self, f: F) -> bool
+        where F: Fn(T) -> bool + Send + Sync + 'static
+    {
+        !self.lake_any(move |item| !f(item))
+    }
+
+    ///Collect the items, in order
+    pub fn lake_collect(self) -> Vec<T> {
+        self._items
+    }
+
+    ///Run `f` on one chunk of the items per thread, returning the result for each chunk in order
+    fn run<R, F>(self, f: F) -> Vec<R>
+        where R: Send + 'static, F: Fn(Vec<T>) -> R + Send + Sync + 'static
+    {
+        //Threads with nothing to do are not worth spawning
+        let threads = self._threads.min(self._items.len()).max(1);
+
+        let lake = Builder::with_data(threads, (chunks(self._items, threads), f))
+            .spawn(|x: ThreadUtilities<(Chunks<T>, F)>| {
+                let (chunks, f) = x.data();
+                f(chunks[x.index()].lock().unwrap().take().unwrap())
+            });
+
+        lake.join_iter().map(|result| result.expect("a thread panicked")).collect()
+    }
+}
+
+///Split the items into one chunk per thread, using the same ranges as [`ThreadUtilities::range`]
+fn chunks<T>(mut items: Vec<T>, threads: usize) -> Chunks<T> {
+    let width = items.len() / threads;
+
+    let mut chunks: Vec<_> = (1..threads).rev().map(|index| Mutex::new(Some(items.split_off(index * width)))).collect();
+    chunks.push(Mutex::new(Some(items)));
+    chunks.reverse();
+
+    chunks
+}
+
+///Parallel versions of common iterator operations, run on a lake
+///
+/// Each method is shorthand for [`LakeIterExt::into_lake`] followed by the method of the same name on [`LakeIter`],
+/// using the full available parallelism.
+pub trait LakeIterExt<T: Send + 'static>: Sized {
+
+    ///Get the items ready for parallel processing
+    fn into_lake(self) -> LakeIter<T>;
+
+    ///See [`LakeIter::lake_map`]
+    fn lake_map<U, F>(self, f: F) -> LakeIter<U>
+        where U: Send + 'static, F: Fn(T) -> U + Send + Sync + 'static
+    {
+        self.into_lake().lake_map(f)
+    }
+
+    ///See [`LakeIter::lake_filter`]
+    fn lake_filter<F>(self, f: F) -> LakeIter<T>
+        where F: Fn(&T) -> bool + Send + Sync + 'static
+    {
+        self.into_lake().lake_filter(f)
+    }
+
+    ///See [`LakeIter::lake_for_each`]
+    fn lake_for_each<F>(self, f: F)
+        where F: Fn(T) + Send + Sync + 'static
+    {
+        self.into_lake().lake_for_each(f)
+    }
+
+    ///See [`LakeIter::lake_reduce`]
+    fn lake_reduce<F>(self, f: F) -> Option<T>
+        where F: Fn(T, T) -> T + Send + Sync + 'static
+    {
+        self.into_lake().lake_reduce(f)
+    }
+
+    ///See [`LakeIter::lake_sum`]
+    fn lake_sum<S>(self) -> S
+        where S: Sum<T> + Sum<S> + Send + 'static
+    {
+        self.into_lake().lake_sum()
+    }
+
+    ///See [`LakeIter::lake_any`]
+    fn lake_any<F>(self, f: F) -> bool
+        where F: Fn(T) -> bool + Send + Sync + 'static
+    {
+        self.into_lake().lake_any(f)
+    }
+
+    ///See [`LakeIter::lake_all`]
+    fn lake_all<F>(self, f: F) -> bool
+        where F: Fn(T) -> bool + Send + Sync + 'static
+    {
+        self.into_lake().lake_all(f)
+    }
+
+    ///See [`LakeIter::lake_collect`]
+    fn lake_collect(self) -> Vec<T> {
+        self.into_lake().lake_collect()
+    }
+}
+
+impl<T: Send + 'static> LakeIterExt<T> for Vec<T> {
+    fn into_lake(self) -> LakeIter<T> {
+        LakeIter { _items: self, _threads: 0 }.threads(FullParallelism)
+    }
+}
+
+///Slices are copied, since the threads of a lake cannot borrow from the caller
+impl<T: Clone + Send + 'static> LakeIterExt<T> for &[T] {
+    fn into_lake(self) -> LakeIter<T> {
+        self.to_vec().into_lake()
+    }
+}
@@ -0,0 +1,188 @@
+use std::any::Any;
+use std::panic::resume_unwind;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::builder::Builder;
+use crate::threadlake::ThreadLake;
+use crate::threadutilities::ThreadUtilities;
+use crate::queue::{Queue, Close};
+use crate::channels::Overflow;
+
+///A stage's lake, with its types erased so stages of different types can be kept together
+trait Stage {
+    fn stop(&self);
+    ///Join every thread, returning the first panic, if any thread panicked
+    fn join(self: Box<Self>) -> Option<Box<dyn Any + Send>>;
+}
+
+impl<D, R, M> Stage for ThreadLake<D, R, M>
+    where M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static
+{
+    fn stop(&self) {
+        ThreadLake::stop(self)
+    }
+
+    fn join(self: Box<Self>) -> Option<Box<dyn Any + Send>> {
+        //Every thread is joined, even after one has panicked
+        self.join_iter().filter_map(|result| result.err()).reduce(|first, _| first)
+    }
+}
+
+///Closes a stage's queues when its last thread finishes, even if it panicked
+///
+/// Closing the output lets the next stage finish once it is drained, and closing the input stops the previous stage
+/// blocking forever on a queue nobody is reading.
+struct Finish<'a, T: Send, U: Send> {
+    _input: & 'a Queue<T>,
+    _output: & 'a Queue<U>,
+    _live: & 'a AtomicUsize,
+}
+
+impl<'a, T: Send, U: Send> Drop for Finish<'a, T, U> {
+    fn drop(&mut self) {
+        if self._live.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self._input.close();
+            self._output.close();
+        }
+    }
+}
+
+///Closes the queue fed by the source when its thread finishes, even if the source panicked
+struct Exhausted<'a, T: Send>(& 'a Queue<T>);
+
+impl<'a, T: Send> Drop for Exhausted<'a, T> {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+///A chain of lakes connected by bounded queues, where each stage has its own number of threads
+///
+/// Items flow from a source, through each stage in turn, and out of the pipeline as an iterator. Since the queues are
+/// bounded, a slow stage makes the stages before it wait rather than letting items pile up. When the source runs dry,
+/// each stage finishes the items it has been given before passing the end on to the next.
+pub struct Pipeline<T> {
+    _stages: Vec<Box<dyn Stage>>,
+    _queues: Vec<Arc<dyn Close>>,
+    _output: Arc<Queue<T>>,
+    _capacity: usize,
+}
+
+impl<T: Send + 'static> Pipeline<T> {
+
+    ///Start a pipeline, with a thread feeding items from `source` into the first queue. Queues between stages hold at most `capacity` items
+    pub fn source<I>(capacity: usize, source: I) -> Self
+        where I: IntoIterator<Item = T>, I::IntoIter: Send + 'static
+    {
+        assert!(capacity > 0, "queues between stages need room for at least one item");
+
+        let output = Arc::new(Queue::new(Some(capacity), Overflow::Block, None));
+
+        let lake = Builder::with_data(1, (Mutex::new(Some(source.into_iter())), output.clone()))
+            .spawn(|x: ThreadUtilities<_>| {
+                let (source, output) = x.data();
+
+                let _exhausted = Exhausted(&**output);
+
+                for item in source.lock().unwrap().take().unwrap() {
+                    if x.check() || output.push(item).is_err() {
+                        break
+                    }
+                }
+            });
+
+        Self { _stages: vec![Box::new(lake)], _queues: vec![output.clone()], _output: output, _capacity: capacity }
+    }
+
+    ///Add a stage, where the threads of a lake built by `builder` take items from the previous stage, call `f` on them and pass the results on
+    ///
+    /// The builder sets the stage's number of threads, along with anything else set on it, such as thread names.
+    /// Panics if the builder has no threads, as nothing would ever take items from the previous stage
+    pub fn stage<U, F>(mut self, builder: Builder<()>, f: F) -> Pipeline<U>
+        where U: Send + 'static, F: Fn(T) -> U + Send + Sync + 'static
+    {
+        assert!(builder.thread_count() > 0, "a stage needs at least one thread");
+
+        let output = Arc::new(Queue::new(Some(self._capacity), Overflow::Block, None));
+        let live = Arc::new(AtomicUsize::new(0));
+
+        let builder = builder.replace_data((self._output.clone(), output.clone(), live.clone(), f))
+            .shared_queue(self._output.clone());
+
+        //Every thread counts itself out when it finishes, so the count has to be in place before they start
+        live.store(builder.thread_count(), Ordering::Release);
+
+        let lake = builder.spawn(|x: ThreadUtilities<_>| {
+            let (input, output, live, f) = x.data();
+
+            let _finish = Finish { _input: &**input, _output: &**output, _live: live };
+
+            while let Some(item) = x.next_item::<T>() {
+                if output.push(f(item)).is_err() {
+                    break
+                }
+            }
+        });
+
+        self._stages.push(Box::new(lake));
+        self._queues.push(output.clone());
+
+        Pipeline {
+            _stages: std::mem::take(&mut self._stages),
+            _queues: std::mem::take(&mut self._queues),
+            _output: output,
+            _capacity: self._capacity,
+        }
+    }
+
+    ///Wait for every stage to finish, discarding any output not yet taken. Returns false if any thread panicked
+    pub fn join(mut self) -> bool {
+        while self._output.pop().is_some() {}
+
+        self.finish().is_none()
+    }
+}
+
+impl<T> Pipeline<T> {
+
+    ///Join every stage, returning the first panic from any of them
+    fn finish(&mut self) -> Option<Box<dyn Any + Send>> {
+        std::mem::take(&mut self._stages).into_iter().filter_map(|stage| stage.join()).reduce(|first, _| first)
+    }
+
+    ///Stop every stage. Threads finish the item they are working on, then items still in the queues are discarded
+    pub fn stop(&self) {
+        for stage in &self._stages {
+            stage.stop();
+        }
+
+        for queue in &self._queues {
+            queue.close();
+        }
+    }
+}
+
+impl<T> Iterator for Pipeline<T> {
+    type Item = T;
+
+    ///Wait for the next item out of the last stage, until the pipeline has been drained
+    ///
+    /// Once it has, the stages are joined, and if any of their threads panicked, the panic is passed on
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self._output.pop();
+
+        if item.is_none() {
+            if let Some(payload) = self.finish() {
+                resume_unwind(payload)
+            }
+        }
+
+        item
+    }
+}
+
+impl<T> Drop for Pipeline<T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, Condvar, Arc};
+use std::time::{Duration, Instant};
+use crate::channels::Overflow;
+
+///Result of trying to take an item out of a [`Queue`]
+pub (crate) enum Pop<T> {
+    Item(T),
+    Empty,
+    Closed,
+}
+
+///Counter bumped every time something happens to a group of queues, so one thread can wait on all of them at once
+pub (crate) struct Activity {
+    _count: Mutex<u64>,
+    _changed: Condvar,
+}
+
+impl Activity {
+
+    pub (crate) fn new() -> Self {
+        Self { _count: Mutex::new(0), _changed: Condvar::new() }
+    }
+
+    ///The current value of the counter
+    pub (crate) fn current(&self) -> u64 {
+        *self._count.lock().unwrap()
+    }
+
+    ///Bump the counter and wake all waiting threads
+    pub (crate) fn notify(&self) {
+        *self._count.lock().unwrap() += 1;
+        self._changed.notify_all();
+    }
+
+    ///Wait until the counter moves past `seen`, returns false if the deadline passes first
+    pub (crate) fn wait_past(&self, seen: u64, deadline: Option<Instant>) -> bool {
+        let mut count = self._count.lock().unwrap();
+
+        while *count == seen {
+            match deadline {
+                None => count = self._changed.wait(count).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false
+                    }
+                    count = self._changed.wait_timeout(count, deadline - now).unwrap().0;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+struct State<T> {
+    _items: VecDeque<T>,
+    _closed: bool,
+    _dropped: usize,
+}
+
+///A multi-producer multi-consumer FIFO queue, optionally bounded, that can be closed from either end
+pub (crate) struct Queue<T> {
+    _state: Mutex<State<T>>,
+    _not_empty: Condvar,
+    _not_full: Condvar,
+    _capacity: Option<usize>,
+    _overflow: Overflow,
+    _activity: Option<Arc<Activity>>,
+}
+
+impl<T> Queue<T> {
+
+    pub (crate) fn new(capacity: Option<usize>, overflow: Overflow, activity: Option<Arc<Activity>>) -> Self {
+        Self {
+            _state: Mutex::new(State { _items: VecDeque::new(), _closed: false, _dropped: 0 }),
+            _not_empty: Condvar::new(),
+            _not_full: Condvar::new(),
+            _capacity: capacity,
+            _overflow: overflow,
+            _activity: activity,
+        }
+    }
+
+    fn notify(&self) {
+        if let Some(activity) = &self._activity {
+            activity.notify();
+        }
+    }
+
+    ///Push an item, applying the overflow policy if the queue is full. Fails if the queue is closed
+    pub (crate) fn push(&self, item: T) -> Result<(), T> {
+        {
+            let mut state = self._state.lock().unwrap();
+
+            if let Some(capacity) = self._capacity {
+                while !state._closed && state._items.len() >= capacity {
+                    match self._overflow {
+                        Overflow::Block => state = self._not_full.wait(state).unwrap(),
+                        Overflow::DropNewest => {
+                            state._dropped += 1;
+                            return Ok(())
+                        }
+                        Overflow::DropOldest => {
+                            state._items.pop_front();
+                            state._dropped += 1;
+                        }
+                    }
+                }
+            }
+
+            if state._closed {
+                return Err(item)
+            }
+
+            state._items.push_back(item);
+        }
+
+        self._not_empty.notify_one();
+        self.notify();
+        Ok(())
+    }
+
+    ///Take an item without blocking
+    pub (crate) fn try_pop(&self) -> Pop<T> {
+        self.pop_deadline(Some(Instant::now()))
+    }
+
+    ///Take an item, waiting at most `timeout` for one to arrive
+    pub (crate) fn pop_timeout(&self, timeout: Duration) -> Pop<T> {
+        self.pop_deadline(Some(Instant::now() + timeout))
+    }
+
+    ///Take an item, waiting until one arrives or the queue is closed and drained
+    pub (crate) fn pop(&self) -> Option<T> {
+        match self.pop_deadline(None) {
+            Pop::Item(item) => Some(item),
+            _ => None,
+        }
+    }
+
+    fn pop_deadline(&self, deadline: Option<Instant>) -> Pop<T> {
+        let mut state = self._state.lock().unwrap();
+
+        loop {
+            if let Some(item) = state._items.pop_front() {
+                drop(state);
+                self._not_full.notify_one();
+                return Pop::Item(item)
+            }
+
+            if state._closed {
+                return Pop::Closed
+            }
+
+            match deadline {
+                None => state = self._not_empty.wait(state).unwrap(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Pop::Empty
+                    }
+                    state = self._not_empty.wait_timeout(state, deadline - now).unwrap().0;
+                }
+            }
+        }
+    }
+
+    ///Close the queue. Pushing fails from now on, popping drains what is left then reports closed
+    pub (crate) fn close(&self) {
+        self._state.lock().unwrap()._closed = true;
+        self._not_empty.notify_all();
+        self._not_full.notify_all();
+        self.notify();
+    }
+
+    ///Number of items discarded by the overflow policy
+    pub (crate) fn dropped(&self) -> usize {
+        self._state.lock().unwrap()._dropped
+    }
+}
+
+///Type erased access to closing a queue
+pub (crate) trait Close: Send + Sync {
+    fn close(&self);
+}
+
+impl<T: Send> Close for Queue<T> {
+    fn close(&self) {
+        Queue::close(self)
+    }
+}
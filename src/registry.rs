@@ -0,0 +1,69 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::queue::{Queue, Close};
+use crate::channels::Overflow;
+use crate::router::{Router, Depart};
+
+///Shared objects declared on the builder, such as watch slots, keyed by their type
+pub (crate) struct Registry {
+    _entries: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    _queues: Vec<Arc<dyn Close>>, //Work queues and routers, closed when the lake is joined
+    _routers: Vec<Arc<dyn Depart>>,
+}
+
+impl Registry {
+
+    pub (crate) fn new() -> Self {
+        Self { _entries: HashMap::new(), _queues: Vec::new(), _routers: Vec::new() }
+    }
+
+    ///Add an object, replacing any previous object of the same type
+    pub (crate) fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self._entries.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    ///Add a work queue for items of type `T`, replacing any previous queue of the same type
+    pub (crate) fn insert_queue<T: Send + 'static>(&mut self, capacity: Option<usize>) {
+        self.insert_shared_queue(Arc::new(Queue::<T>::new(capacity, Overflow::Block, None)));
+    }
+
+    ///Add a work queue that is also fed from outside the lake, such as the output of another lake
+    pub (crate) fn insert_shared_queue<T: Send + 'static>(&mut self, queue: Arc<Queue<T>>) {
+        self.insert_closing(queue);
+    }
+
+    ///Add an object that is closed when the lake is joined, such as a router
+    pub (crate) fn insert_closing<T: Close + 'static>(&mut self, value: Arc<T>) {
+        self._entries.insert(TypeId::of::<T>(), value.clone());
+        self._queues.push(value);
+    }
+
+    ///Add a router for messages of type `T`, which is closed when the lake is joined and left by each thread as it finishes
+    pub (crate) fn insert_router<T: Send + 'static>(&mut self, router: Arc<Router<T>>) {
+        self.insert_closing(router.clone());
+        self._routers.push(router);
+    }
+
+    ///Take a thread out of every router, once its closure has returned
+    pub (crate) fn depart_routers(&self, index: usize) {
+        for router in &self._routers {
+            router.depart(index);
+        }
+    }
+
+    ///Close every work queue and router, so threads waiting for items finish once they are drained
+    pub (crate) fn close_queues(&self) {
+        for queue in &self._queues {
+            queue.close();
+        }
+    }
+
+    pub (crate) fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self._entries.get(&TypeId::of::<T>()).and_then(|entry| entry.downcast_ref())
+    }
+
+    pub (crate) fn get_arc<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self._entries.get(&TypeId::of::<T>()).and_then(|entry| entry.clone().downcast().ok())
+    }
+}
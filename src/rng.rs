@@ -0,0 +1,62 @@
+///Added to the counter for every number drawn, as in SplitMix64. The fractional part of the golden ratio, made odd
+const GAMMA: u64 = 0x9E3779B97F4A7C15;
+
+///The seed set with [`crate::builder::Builder::seed`], kept in the lake's registry
+pub (crate) struct Seed(pub (crate) u64);
+
+///The SplitMix64 finaliser, which scrambles the bits of `z` so that nearby inputs give unrelated outputs
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+///A small, fast, counter-based random number generator, for simulations rather than cryptography
+///
+/// The n-th number of a stream is a hash of the stream's key and n, so a stream is fixed by the seed and stream number
+/// alone. Get one for a block of work with [`crate::threadutilities::ThreadUtilities::rng`].
+#[derive(Clone, Debug)]
+pub struct Rng {
+    _key: u64,
+    _counter: u64,
+}
+
+impl Rng {
+
+    ///Create the stream numbered `stream` for `seed`
+    pub fn new(seed: u64, stream: u64) -> Self {
+        Self { _key: mix(mix(seed) ^ mix(stream.wrapping_add(GAMMA))), _counter: 0 }
+    }
+
+    ///The next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self._counter = self._counter.wrapping_add(1);
+        mix(self._key.wrapping_add(self._counter.wrapping_mul(GAMMA)))
+    }
+
+    ///The next 32 random bits
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    ///A random number in `0.0..1.0`, with 53 bits of precision
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    ///A random number in `0..bound`, with every value equally likely. Panics if `bound` is zero
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "cannot pick a number below zero");
+
+        //Numbers from the top, incomplete copy of 0..bound are rejected, so none are favoured
+        let limit = u64::MAX - u64::MAX % bound;
+
+        loop {
+            let value = self.next_u64();
+
+            if value < limit {
+                return value % bound
+            }
+        }
+    }
+}
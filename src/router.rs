@@ -0,0 +1,130 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, fence};
+use std::time::Duration;
+use crate::queue::{Queue, Close, Pop};
+use crate::channels::Overflow;
+use crate::registry::Registry;
+
+///An inbox for every thread in a lake, with messages of type `T` delivered to the thread that owns their key
+///
+/// A message counts as outstanding from the moment it is routed until its owner asks for the next one, so a thread
+/// handling a message can route more without the others deciding that the work is done.
+pub (crate) struct Router<T> {
+    _inboxes: Vec<Queue<T>>,
+    _holding: Vec<AtomicBool>, //Whether each thread is still handling the last message it took
+    _departed: Vec<AtomicBool>, //Whether each thread's closure has returned, so it takes no more messages
+    _outstanding: AtomicUsize,
+    _closed: AtomicBool, //Set once the lake will route no more messages
+}
+
+impl<T: Send> Router<T> {
+
+    pub (crate) fn new(threads: usize) -> Self {
+        Self {
+            _inboxes: (0..threads).map(|_| Queue::new(None, Overflow::Block, None)).collect(),
+            _holding: (0..threads).map(|_| AtomicBool::new(false)).collect(),
+            _departed: (0..threads).map(|_| AtomicBool::new(false)).collect(),
+            _outstanding: AtomicUsize::new(0),
+            _closed: AtomicBool::new(false),
+        }
+    }
+
+    ///The index of the thread that owns `key`. Every thread hashes keys the same way
+    pub (crate) fn owner<K: Hash + ?Sized>(&self, key: &K) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self._inboxes.len() as u64) as usize
+    }
+
+    pub (crate) fn send<K: Hash + ?Sized>(&self, key: &K, message: T) {
+        self.send_to(self.owner(key), message);
+    }
+
+    pub (crate) fn send_to(&self, owner: usize, message: T) {
+        self._outstanding.fetch_add(1, Ordering::AcqRel);
+
+        //Inboxes are never closed, so this cannot fail
+        let _ = self._inboxes[owner].push(message);
+
+        //Either this sees the owner has departed, or the owner's departure sees the message, so it is never stranded
+        fence(Ordering::SeqCst);
+
+        if self._departed[owner].load(Ordering::Relaxed) {
+            self.discard(owner);
+        }
+    }
+
+    pub (crate) fn closed(&self) -> bool {
+        self._closed.load(Ordering::Acquire)
+    }
+
+    ///Returns true once no more messages can arrive
+    pub (crate) fn finished(&self) -> bool {
+        self.closed() && self._outstanding.load(Ordering::Acquire) == 0
+    }
+
+    ///Mark the last message taken by a thread as handled
+    pub (crate) fn release(&self, index: usize) {
+        if self._holding[index].swap(false, Ordering::AcqRel) {
+            self._outstanding.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    pub (crate) fn try_take(&self, index: usize) -> Option<T> {
+        match self._inboxes[index].try_pop() {
+            Pop::Item(message) => Some(self.hold(index, message)),
+            _ => None,
+        }
+    }
+
+    pub (crate) fn take_timeout(&self, index: usize, timeout: Duration) -> Option<T> {
+        match self._inboxes[index].pop_timeout(timeout) {
+            Pop::Item(message) => Some(self.hold(index, message)),
+            _ => None,
+        }
+    }
+
+    ///Drop every message waiting in a thread's inbox
+    fn discard(&self, index: usize) {
+        while let Pop::Item(_) = self._inboxes[index].try_pop() {
+            self._outstanding.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
+    fn hold(&self, index: usize, message: T) -> T {
+        self._holding[index].store(true, Ordering::Release);
+        message
+    }
+}
+
+impl<T: Send> Close for Router<T> {
+    fn close(&self) {
+        self._closed.store(true, Ordering::Release);
+    }
+}
+
+///Routers with their types erased, so a thread can leave all of them when its closure returns
+pub (crate) trait Depart: Send + Sync {
+    ///Stop counting a thread: release the message it holds, and drop any messages sent to it now or later
+    fn depart(&self, index: usize);
+}
+
+impl<T: Send> Depart for Router<T> {
+    fn depart(&self, index: usize) {
+        self._departed[index].store(true, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+
+        self.release(index);
+        self.discard(index);
+    }
+}
+
+///Takes a thread out of every router when its closure finishes, however it finishes
+pub (crate) struct Departing<'a>(pub (crate) &'a Registry, pub (crate) usize);
+
+impl<'a> Drop for Departing<'a> {
+    fn drop(&mut self) {
+        self.0.depart_routers(self.1);
+    }
+}
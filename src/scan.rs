@@ -0,0 +1,62 @@
+use crate::builder::Builder;
+use crate::disjointer::Disjointer;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+struct Scan<T, F> {
+    _data: Disjointer<T>,
+    _identity: Option<T>, //Only set for exclusive scans
+    _op: F,
+}
+
+///Scan `data` in place on a lake, by moving it into a [`Disjointer`] for [`ThreadUtilities::scan_inclusive`] or
+/// [`ThreadUtilities::scan_exclusive`] and back out again
+fn scan<C, T, F>(thread_count: C, data: &mut Vec<T>, identity: Option<T>, op: F)
+    where C: ThreadCount, T: Clone + Send + Sync + 'static, F: Fn(&T, &T) -> T + Send + Sync + 'static
+{
+    let threads = threads_for(thread_count, data.len());
+
+    if data.is_empty() {
+        return
+    }
+
+    let lake = Builder::with_data(threads, Scan { _data: Disjointer::new(std::mem::take(data)), _identity: identity, _op: op })
+        .spawn(|x: ThreadUtilities<Scan<T, F>>| {
+            let scan = x.data();
+
+            match &scan._identity {
+                None => x.scan_inclusive(&scan._data, &scan._op),
+                Some(identity) => x.scan_exclusive(&scan._data, identity.clone(), &scan._op),
+            }
+        });
+
+    let (_, scan) = lake.join_with_data();
+
+    *data = scan._data.take();
+}
+
+///Replace every element of `data` with `op` applied to it and every element before it, in place on a lake
+///
+/// The result for index `i` is `data[0] op data[1] op ... op data[i]`. Since threads combine their pieces separately,
+/// `op` must be associative, but it need not be commutative.
+///
+/// If `op` panics, the panic is passed on and the vector is left empty. To scan data that already lives in a lake, use
+/// [`ThreadUtilities::scan_inclusive`]
+pub fn lake_scan_inclusive<C, T, F>(thread_count: C, data: &mut Vec<T>, op: F)
+    where C: ThreadCount, T: Clone + Send + Sync + 'static, F: Fn(&T, &T) -> T + Send + Sync + 'static
+{
+    scan(thread_count, data, None, op)
+}
+
+///Replace every element of `data` with `op` applied to every element before it, in place on a lake
+///
+/// The result for index `i` is `identity op data[0] op ... op data[i - 1]`, so the first element becomes `identity`.
+/// As with [`lake_scan_inclusive`], `op` must be associative, and `identity` must leave any value unchanged.
+///
+/// If `op` panics, the panic is passed on and the vector is left empty. To scan data that already lives in a lake, use
+/// [`ThreadUtilities::scan_exclusive`]
+pub fn lake_scan_exclusive<C, T, F>(thread_count: C, data: &mut Vec<T>, identity: T, op: F)
+    where C: ThreadCount, T: Clone + Send + Sync + 'static, F: Fn(&T, &T) -> T + Send + Sync + 'static
+{
+    scan(thread_count, data, Some(identity), op)
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::split::{SubSlice, SubSliceMut};
+use crate::disjointer::Disjointer;
+
+///The shared cursors behind dynamic and guided schedules
+///
+/// The n-th schedule each thread starts claims from the n-th cursor, so threads working through the same loops in
+/// the same order share one cursor per loop without having to name it. A cursor is only kept here until every thread
+/// has picked it up, after which the schedules themselves hold the only references to it, so a lake that starts a
+/// schedule every iteration does not build up cursors. A thread that finishes without starting every schedule the
+/// others did leaves those cursors behind until the lake is dropped.
+pub (crate) struct Cursors {
+    _cursors: Mutex<HashMap<usize, (Arc<AtomicUsize>, usize)>>, //Each cursor, with the number of threads that have picked it up
+    _threads: usize,
+}
+
+impl Cursors {
+
+    pub (crate) fn new(threads: usize) -> Self {
+        Self { _cursors: Mutex::new(HashMap::new()), _threads: threads }
+    }
+
+    pub (crate) fn get(&self, n: usize) -> Arc<AtomicUsize> {
+        let mut cursors = self._cursors.lock().unwrap();
+
+        let (cursor, picked) = cursors.entry(n).or_insert_with(|| (Arc::new(AtomicUsize::new(0)), 0));
+        let cursor = cursor.clone();
+        *picked += 1;
+
+        //Every thread starts each schedule once, so nobody else will ask for this cursor
+        if *picked == self._threads {
+            cursors.remove(&n);
+        }
+
+        cursor
+    }
+
+    ///Number of cursors still waiting for a thread to pick them up
+    #[cfg(test)]
+    pub (crate) fn len(&self) -> usize {
+        self._cursors.lock().unwrap().len()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub (crate) enum Kind {
+    Dynamic(usize),
+    Guided(usize),
+}
+
+///Iterates over the sub-ranges of `0..total` claimed by one thread from a dynamic or guided schedule
+///
+/// Created with [`crate::threadutilities::ThreadUtilities::dynamic_range`] or [`crate::threadutilities::ThreadUtilities::guided_range`].
+/// Each call to `next` claims the next chunk that no other thread has claimed, so threads that finish their chunks
+/// quickly go on to claim more of them.
+pub struct Schedule {
+    pub (crate) _cursor: Arc<AtomicUsize>,
+    pub (crate) _total: usize,
+    pub (crate) _threads: usize,
+    pub (crate) _kind: Kind,
+}
+
+impl Iterator for Schedule {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self._kind {
+            Kind::Dynamic(chunk) => {
+                let total = self._total;
+
+                //The cursor never moves past the end, so it cannot overflow however large the chunks or however often it is called
+                let start = self._cursor.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |start| {
+                    (start < total).then(|| start.saturating_add(chunk).min(total))
+                }).ok()?;
+
+                Some(start..start.saturating_add(chunk).min(total))
+            }
+            Kind::Guided(min_chunk) => {
+                let mut start = self._cursor.load(Ordering::Relaxed);
+
+                loop {
+                    if start >= self._total {
+                        return None
+                    }
+
+                    let remaining = self._total - start;
+                    let chunk = (remaining / self._threads).max(min_chunk).min(remaining);
+
+                    match self._cursor.compare_exchange_weak(start, start + chunk, Ordering::Relaxed, Ordering::Relaxed) {
+                        Ok(_) => return Some(start..start + chunk),
+                        Err(current) => start = current,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Schedule {
+
+    ///Claim subslices of `slice` instead of ranges. The schedule should have been created with the length of `slice`
+    pub fn slices<S>(self, slice: &[S]) -> ScheduledSlices<'_, S> {
+        ScheduledSlices { _schedule: self, _slice: slice }
+    }
+}
+
+///Iterates over the subslices claimed by a [`Schedule`]
+pub struct ScheduledSlices<'s, S> {
+    _schedule: Schedule,
+    _slice: & 's [S],
+}
+
+impl<'s, S> Iterator for ScheduledSlices<'s, S> {
+    type Item = SubSlice<'s, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = self._schedule.next()?;
+
+        Some(SubSlice { _width: range.len(), _offset: range.start, _slice: &self._slice[range] })
+    }
+}
+
+///Iterates over the mutable subslices of a [`Disjointer`] claimed by a [`Schedule`]
+pub struct ScheduledPieces<'a, T> {
+    pub (crate) _schedule: Schedule,
+    pub (crate) _disjointer: & 'a Disjointer<T>,
+}
+
+impl<'a, T: 'static> Iterator for ScheduledPieces<'a, T> {
+    type Item = SubSliceMut<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = self._schedule.next()?;
+
+        //Chunks claimed from one cursor never overlap, so no other thread can be given this slice
+        Some(SubSliceMut { _width: range.len(), _offset: range.start, _slice: unsafe { self._disjointer.slice_mut(range) } })
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::builder::Builder;
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+///Number of elements a thread claims at a time, between checks of the best match so far
+const CHUNK: usize = 1024;
+
+///Which end of the data a search wants its match closest to
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    First,
+    Last,
+}
+
+struct Search<T, P> {
+    _data: Arc<[T]>,
+    _predicate: P,
+    _direction: Direction,
+    _best: AtomicUsize, //Closest match found so far, counted from the end being searched from. usize::MAX if there is none yet
+}
+
+///Search for the match closest to one end of `data`, returning its index
+///
+/// Threads claim chunks in order from that end, and stop claiming once every chunk left is further away than the best
+/// match so far. Within a chunk, a thread stops as soon as it passes the best match.
+fn search<C, T, P>(thread_count: C, data: &Arc<[T]>, predicate: P, direction: Direction) -> Option<usize>
+    where C: ThreadCount, T: Send + Sync + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    let threads = threads_for(thread_count, data.len());
+
+    let search = Search { _data: data.clone(), _predicate: predicate, _direction: direction, _best: AtomicUsize::new(usize::MAX) };
+
+    let lake = Builder::with_data(threads, search)
+        .spawn(|x: ThreadUtilities<Search<T, P>>| {
+            let search = x.data();
+            let len = search._data.len();
+
+            for chunk in x.dynamic_range(len, CHUNK) {
+                if chunk.start >= search._best.load(Ordering::Relaxed) {
+                    break
+                }
+
+                for distance in chunk {
+                    if distance >= search._best.load(Ordering::Relaxed) {
+                        break
+                    }
+
+                    let index = match search._direction {
+                        Direction::First => distance,
+                        Direction::Last => len - 1 - distance,
+                    };
+
+                    if (search._predicate)(&search._data[index]) {
+                        search._best.fetch_min(distance, Ordering::Relaxed);
+                        break
+                    }
+                }
+            }
+        });
+
+    let (_, search) = lake.join_with_data();
+
+    match (search._best.load(Ordering::Relaxed), direction) {
+        (usize::MAX, _) => None,
+        (distance, Direction::First) => Some(distance),
+        (distance, Direction::Last) => Some(data.len() - 1 - distance),
+    }
+}
+
+///The index of the first element of `data` matching `predicate`, searched for on a lake
+///
+/// Unlike a plain split of the data, this always finds the lowest matching index, and threads stop early once they can
+/// no longer find a lower one. The lake shares `data` with the caller, so the data is searched in place rather than
+/// copied or consumed.
+pub fn lake_position<C, T, P>(thread_count: C, data: &Arc<[T]>, predicate: P) -> Option<usize>
+    where C: ThreadCount, T: Send + Sync + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    search(thread_count, data, predicate, Direction::First)
+}
+
+///The first element of `data` matching `predicate`, searched for on a lake, see [`lake_position`]
+pub fn lake_find_first<C, T, P>(thread_count: C, data: &Arc<[T]>, predicate: P) -> Option<&T>
+    where C: ThreadCount, T: Send + Sync + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    search(thread_count, data, predicate, Direction::First).map(|index| &data[index])
+}
+
+///The last element of `data` matching `predicate`, searched for on a lake from the end of the data
+pub fn lake_find_last<C, T, P>(thread_count: C, data: &Arc<[T]>, predicate: P) -> Option<&T>
+    where C: ThreadCount, T: Send + Sync + 'static, P: Fn(&T) -> bool + Send + Sync + 'static
+{
+    search(thread_count, data, predicate, Direction::Last).map(|index| &data[index])
+}
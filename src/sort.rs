@@ -0,0 +1,171 @@
+use std::cmp::Ordering;
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::ptr;
+use crate::builder::Builder;
+use crate::disjointer::{Disjointer, into_uninit, from_uninit};
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+///The state shared by the threads of a sort. Elements are moved bitwise between the two buffers, which never drop them
+struct Sort<T, F> {
+    _buffers: [Disjointer<MaybeUninit<T>>; 2],
+    _runs: Vec<usize>, //Start of the piece sorted by each thread, followed by the length
+    _compare: F,
+    _stable: bool,
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Sort<T, F> {
+
+    fn is_less(&self, a: &MaybeUninit<T>, b: &MaybeUninit<T>) -> bool {
+        //Every element read during a merge was moved into the buffer being read by the round before
+        unsafe { (self._compare)(a.assume_init_ref(), b.assume_init_ref()) == Ordering::Less }
+    }
+
+    ///Number of elements to take from `a` for the first `k` elements of the stable merge of `a` and `b`
+    ///
+    /// Equal elements are taken from `a` first, which is what keeps the merge stable
+    fn split(&self, a: &[MaybeUninit<T>], b: &[MaybeUninit<T>], k: usize) -> usize {
+        let (mut low, mut high) = (k.saturating_sub(b.len()), k.min(a.len()));
+
+        while low < high {
+            let middle = (low + high) / 2;
+
+            if self.is_less(&b[k - middle - 1], &a[middle]) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+
+        low
+    }
+
+    ///Merge the elements of `a` and `b` that land in `output` of their merge into `destination`
+    fn merge(&self, a: &[MaybeUninit<T>], b: &[MaybeUninit<T>], output: Range<usize>, destination: &mut [MaybeUninit<T>]) {
+        let mut i = self.split(a, b, output.start);
+        let mut j = output.start - i;
+
+        for slot in destination.iter_mut() {
+            let from_a = j == b.len() || (i < a.len() && !self.is_less(&b[j], &a[i]));
+
+            let source = if from_a { i += 1; &a[i - 1] } else { j += 1; &b[j - 1] };
+
+            unsafe { ptr::copy_nonoverlapping(source, slot, 1) };
+        }
+    }
+}
+
+///Sort `data` on a lake, with `compare` giving the order
+///
+/// Each thread sorts its piece of the data, then the sorted pieces are merged in pairs until one run is left. Each round
+/// of merging is split evenly between all of the threads, by finding where each thread's share of the output starts in
+/// the pair of runs that feed it.
+fn sort<C, T, F>(thread_count: C, data: &mut Vec<T>, compare: F, stable: bool)
+    where C: ThreadCount, T: Send + 'static, F: Fn(&T, &T) -> Ordering + Send + Sync + 'static
+{
+    let len = data.len();
+
+    let threads = threads_for(thread_count, len);
+
+    if len < 2 {
+        return
+    }
+
+    //Once in the buffers, elements are only dropped if they make it back out, so a panic leaks them rather than dropping any twice
+    let width = len / threads;
+    let mut runs: Vec<usize> = (0..threads).map(|index| index * width).collect();
+    runs.push(len);
+
+    let sort = Sort {
+        _buffers: [Disjointer::new(into_uninit(std::mem::take(data))), Disjointer::new((0..len).map(|_| MaybeUninit::uninit()).collect::<Vec<_>>())],
+        _runs: runs,
+        _compare: compare,
+        _stable: stable,
+    };
+
+    let lake = Builder::with_data(threads, sort)
+        .spawn(|x: ThreadUtilities<Sort<T, F>>| {
+            let sort = x.data();
+            let len = sort._buffers[0].len();
+
+            {
+                let piece = sort._buffers[0].piece(&x);
+                let piece = unsafe { &mut *(piece._slice as *mut [MaybeUninit<T>] as *mut [T]) };
+
+                if sort._stable {
+                    piece.sort_by(&sort._compare);
+                } else {
+                    piece.sort_unstable_by(&sort._compare);
+                }
+            }
+
+            let mut runs = sort._runs.clone();
+            let mut source = 0;
+
+            while runs.len() > 2 {
+                x.barrier();
+
+                let output = x.range(len);
+
+                //Nobody writes to the source buffer this round, and every thread writes to its own range of the destination
+                let from = unsafe { sort._buffers[source].slice(0..len) };
+                let to = unsafe { sort._buffers[1 - source].slice_mut(output.clone()) };
+
+                for pair in runs.windows(3).step_by(2).map(|bounds| (bounds[0], bounds[1], bounds[2])).chain(odd_run(&runs)) {
+                    let (start, middle, end) = pair;
+                    let (low, high) = (output.start.max(start), output.end.min(end));
+
+                    if low < high {
+                        sort.merge(&from[start..middle], &from[middle..end], low - start..high - start, &mut to[low - output.start..high - output.start]);
+                    }
+                }
+
+                runs = runs.iter().copied().step_by(2).chain(runs.last().copied().filter(|_| runs.len().is_multiple_of(2))).collect();
+                source = 1 - source;
+            }
+
+            source
+        });
+
+    let (sorted_into, sort) = lake.join_with_data();
+    let [first, second] = sort._buffers;
+
+    *data = unsafe { from_uninit(if sorted_into[0] == 0 { first } else { second }.take()) };
+}
+
+///A run left over at the end of a round with an odd number of runs, which is moved across as it is
+fn odd_run(runs: &[usize]) -> Option<(usize, usize, usize)> {
+    if runs.len().is_multiple_of(2) {
+        let end = runs[runs.len() - 1];
+        Some((runs[runs.len() - 2], end, end))
+    } else {
+        None
+    }
+}
+
+///Sort a vector on a lake. The sort is stable, so equal elements keep their order
+///
+/// If a thread panics, the panic is passed on and the vector is left empty, leaking its elements
+pub fn lake_sort<C: ThreadCount, T: Ord + Send + 'static>(thread_count: C, data: &mut Vec<T>) {
+    sort(thread_count, data, T::cmp, true)
+}
+
+///Sort a vector on a lake with a comparison function, keeping equal elements in order
+pub fn lake_sort_by<C, T, F>(thread_count: C, data: &mut Vec<T>, compare: F)
+    where C: ThreadCount, T: Send + 'static, F: Fn(&T, &T) -> Ordering + Send + Sync + 'static
+{
+    sort(thread_count, data, compare, true)
+}
+
+///Sort a vector on a lake by the key `f` extracts from each element, keeping elements with equal keys in order
+pub fn lake_sort_by_key<C, T, K, F>(thread_count: C, data: &mut Vec<T>, f: F)
+    where C: ThreadCount, T: Send + 'static, K: Ord, F: Fn(&T) -> K + Send + Sync + 'static
+{
+    sort(thread_count, data, move |a, b| f(a).cmp(&f(b)), true)
+}
+
+///Sort a vector on a lake, without keeping equal elements in order, which sorts each thread's piece faster
+pub fn lake_sort_unstable<C: ThreadCount, T: Ord + Send + 'static>(thread_count: C, data: &mut Vec<T>) {
+    sort(thread_count, data, T::cmp, false)
+}
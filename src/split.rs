@@ -5,18 +5,24 @@ use std::slice::SliceIndex;
 pub struct SubSlice<'a, T> {
     pub (crate) _slice: & 'a [T],
     pub (crate) _width: usize,
+    pub (crate) _offset: usize, //Index of the first element in the original slice
 }
 
 ///Represents a mutable subslice created by splitting a slice for a thread
 pub struct SubSliceMut<'a, T> {
     pub (crate) _slice: & 'a mut [T],
     pub (crate) _width: usize,
+    pub (crate) _offset: usize, //Index of the first element in the original slice
 }
 
 impl<'a, T> SubSlice<'a, T> {
 
-    ///Return the width for this split. This loosely represents the length of the slice, unless the number of threads does not divide the length of the
-    /// original slice, then the last slice has length width + remainder
+    ///Return the width for this split, which depends on how the subslice was made
+    ///
+    /// For a subslice from an even split, such as [`crate::threadutilities::ThreadUtilities::split_slice`], this is the
+    /// length of the slice divided by the number of threads, and every subslice has this length except the last, which
+    /// also takes the remainder. For a balanced split or a dynamic or guided schedule, it is the length of this subslice
+    /// alone, so `width() * index` is not its position. Use [`Self::offset`] and [`Self::len`] for that instead
     pub fn width(&self) -> usize {
         self._width
     }
@@ -25,6 +31,32 @@ impl<'a, T> SubSlice<'a, T> {
     pub fn iter(&self) -> std::slice::Iter<'a, T> {
         self._slice.iter()
     }
+
+    ///Return the index in the original slice of the first element of this subslice
+    pub fn offset(&self) -> usize {
+        self._offset
+    }
+
+    ///Return the number of elements in the subslice
+    pub fn len(&self) -> usize {
+        self._slice.len()
+    }
+
+    ///Returns true if the subslice is empty
+    pub fn is_empty(&self) -> bool {
+        self._slice.is_empty()
+    }
+
+    ///Return the index in the original slice of the element at `local` in this subslice
+    pub fn global_index(&self, local: usize) -> usize {
+        self._offset + local
+    }
+
+    ///Return an iterator over the subslice, along with the index of each element in the original slice
+    pub fn enumerate_global(&self) -> impl Iterator<Item = (usize, & 'a T)> {
+        let offset = self._offset;
+        self._slice.iter().enumerate().map(move |(local, element)| (offset + local, element))
+    }
 }
 
 impl<'a, T, I: SliceIndex<[T]>> Index<I> for SubSlice<'a, T> {
@@ -46,8 +78,12 @@ impl<'a, T> IntoIterator for SubSlice<'a, T> {
 
 impl<'a, T> SubSliceMut<'a, T> {
 
-    ///Return the width for this split. This loosely represents the length of the slice, unless the number of threads does not divide the length of the
-    /// original slice, then the last slice has length width + remainder
+    ///Return the width for this split, which depends on how the subslice was made
+    ///
+    /// For a subslice from an even split, such as [`crate::threadutilities::ThreadUtilities::split_slice`], this is the
+    /// length of the slice divided by the number of threads, and every subslice has this length except the last, which
+    /// also takes the remainder. For a balanced split or a dynamic or guided schedule, it is the length of this subslice
+    /// alone, so `width() * index` is not its position. Use [`Self::offset`] and [`Self::len`] for that instead
     pub fn width(&self) -> usize {
         self._width
     }
@@ -61,6 +97,32 @@ impl<'a, T> SubSliceMut<'a, T> {
     pub fn iter_mut(& mut self) -> std::slice::IterMut<T> {
         self._slice.iter_mut()
     }
+
+    ///Return the index in the original slice of the first element of this subslice
+    pub fn offset(&self) -> usize {
+        self._offset
+    }
+
+    ///Return the number of elements in the subslice
+    pub fn len(&self) -> usize {
+        self._slice.len()
+    }
+
+    ///Returns true if the subslice is empty
+    pub fn is_empty(&self) -> bool {
+        self._slice.is_empty()
+    }
+
+    ///Return the index in the original slice of the element at `local` in this subslice
+    pub fn global_index(&self, local: usize) -> usize {
+        self._offset + local
+    }
+
+    ///Return a mutable iterator over the subslice, along with the index of each element in the original slice
+    pub fn enumerate_global(& mut self) -> impl Iterator<Item = (usize, & mut T)> {
+        let offset = self._offset;
+        self._slice.iter_mut().enumerate().map(move |(local, element)| (offset + local, element))
+    }
 }
 
 impl<'a, T, I: SliceIndex<[T]>> Index<I> for SubSliceMut<'a, T> {
@@ -0,0 +1,188 @@
+use std::any::Any;
+use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use crate::threadutilities::ThreadUtilities;
+
+///Runs the job behind a type erased pointer, on whichever thread took it out of a deque
+type Execute<D, M> = unsafe fn(*const (), &ThreadUtilities<D, M>);
+
+///A job waiting in a deque. The job itself lives either on the stack of a thread blocked in
+/// [`ThreadUtilities::join`], or on the heap for tasks from [`ThreadUtilities::spawn_task`]
+pub (crate) struct JobRef<D, M> {
+    _pointer: *const (),
+    _execute: Execute<D, M>,
+}
+
+//Jobs are only ever created from closures that are Send
+unsafe impl<D, M> Send for JobRef<D, M> {}
+
+impl<D, M> JobRef<D, M> {
+    ///Run the job. Jobs catch their own panics, so this never unwinds
+    pub (crate) unsafe fn execute(self, utility: &ThreadUtilities<D, M>) {
+        (self._execute)(self._pointer, utility)
+    }
+}
+
+///The second half of a [`ThreadUtilities::join`], which lives on the stack of the joining thread until it has run
+pub (crate) struct StackJob<F, R> {
+    _func: UnsafeCell<Option<F>>,
+    _result: UnsafeCell<Option<std::thread::Result<R>>>,
+    _done: AtomicBool,
+    _owner: usize, //Index of the joining thread, which is woken up once the job is done
+}
+
+impl<F, R> StackJob<F, R> {
+
+    pub (crate) fn new(func: F, owner: usize) -> Self {
+        Self { _func: UnsafeCell::new(Some(func)), _result: UnsafeCell::new(None), _done: AtomicBool::new(false), _owner: owner }
+    }
+
+    pub (crate) fn done(&self) -> bool {
+        self._done.load(Ordering::Acquire)
+    }
+
+    ///A reference to put in a deque. The job must not move or be dropped until it is done
+    pub (crate) fn as_job_ref<D, M>(&self) -> JobRef<D, M>
+        where F: FnOnce(&ThreadUtilities<D, M>) -> R + Send, R: Send
+    {
+        JobRef { _pointer: self as *const Self as *const (), _execute: Self::execute::<D, M> }
+    }
+
+    unsafe fn execute<D, M>(this: *const (), utility: &ThreadUtilities<D, M>)
+        where F: FnOnce(&ThreadUtilities<D, M>) -> R + Send, R: Send
+    {
+        let job = &*(this as *const Self);
+        let func = (*job._func.get()).take().unwrap();
+
+        *job._result.get() = Some(catch_unwind(AssertUnwindSafe(|| func(utility))));
+
+        let owner = job._owner;
+
+        //The owner may free the job as soon as it sees this, so it must be the last thing we touch
+        job._done.store(true, Ordering::Release);
+
+        utility._tasks.wake(owner);
+    }
+
+    ///Take the result once the job is done
+    pub (crate) fn into_result(self) -> std::thread::Result<R> {
+        self._result.into_inner().unwrap()
+    }
+}
+
+///A job from [`ThreadUtilities::spawn_task`], boxed so it can outlive the thread that spawned it
+pub (crate) fn heap_job<D, M, F>(task: F) -> JobRef<D, M>
+    where F: FnOnce(&ThreadUtilities<D, M>) + Send + 'static
+{
+    unsafe fn execute<D, M, F>(this: *const (), utility: &ThreadUtilities<D, M>)
+        where F: FnOnce(&ThreadUtilities<D, M>) + Send + 'static
+    {
+        let task = Box::from_raw(this as *mut F);
+
+        //Tasks wait while the lake is paused, and are dropped without running once it is stopped
+        if !utility.check() {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| task(utility))) {
+                utility._tasks._panics.lock().unwrap().push(payload);
+            }
+        }
+
+        utility._tasks._pending.fetch_sub(1, Ordering::Release);
+    }
+
+    JobRef { _pointer: Box::into_raw(Box::new(task)) as *const (), _execute: execute::<D, M, F> }
+}
+
+///A deque of jobs for every thread in a lake. Threads push and pop at the back of their own deque, and steal from the front of the others
+pub (crate) struct TaskPool<D, M> {
+    _deques: Vec<Mutex<VecDeque<JobRef<D, M>>>>,
+    _sleepers: Vec<(Mutex<()>, Condvar)>, //Where each thread blocks while waiting for a job it forked to be done
+    pub (crate) _pending: AtomicUsize, //Spawned tasks that have not finished yet
+    _running: AtomicUsize, //Threads still running their closure, which may fork more work
+    _used: AtomicBool, //Set once any thread forks or spawns a task, so lakes that never do can skip helping
+    pub (crate) _panics: Mutex<Vec<Box<dyn Any + Send>>>,
+}
+
+impl<D, M> TaskPool<D, M> {
+
+    pub (crate) fn new(threads: usize) -> Self {
+        Self {
+            _deques: (0..threads).map(|_| Mutex::new(VecDeque::new())).collect(),
+            _sleepers: (0..threads).map(|_| (Mutex::new(()), Condvar::new())).collect(),
+            _pending: AtomicUsize::new(0),
+            _running: AtomicUsize::new(threads),
+            _used: AtomicBool::new(false),
+            _panics: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub (crate) fn push(&self, index: usize, job: JobRef<D, M>) {
+        self._used.store(true, Ordering::Release);
+        self._deques[index].lock().unwrap().push_back(job);
+    }
+
+    ///Returns true if any thread has forked or spawned a task in this lake
+    pub (crate) fn used(&self) -> bool {
+        self._used.load(Ordering::Acquire)
+    }
+
+    ///Find a job, first from the back of our own deque, then from the front of everybody else's
+    pub (crate) fn find(&self, index: usize) -> Option<JobRef<D, M>> {
+        if let Some(job) = self._deques[index].lock().unwrap().pop_back() {
+            return Some(job)
+        }
+
+        let count = self._deques.len();
+
+        (1..count).find_map(|offset| self._deques[(index + offset) % count].lock().unwrap().pop_front())
+    }
+
+    ///Block thread `index` until `done` returns true, or until `timeout` has passed so it can look for work to steal again
+    pub (crate) fn sleep<F: Fn() -> bool>(&self, index: usize, done: F, timeout: Duration) {
+        let (lock, condvar) = &self._sleepers[index];
+        let guard = lock.lock().unwrap();
+
+        //Jobs are marked done before the owner is woken, and waking takes the lock, so the wake up cannot be missed
+        if !done() {
+            drop(condvar.wait_timeout(guard, timeout).unwrap());
+        }
+    }
+
+    ///Wake up thread `index` if it is sleeping
+    pub (crate) fn wake(&self, index: usize) {
+        let (lock, condvar) = &self._sleepers[index];
+        let _guard = lock.lock().unwrap();
+        condvar.notify_all();
+    }
+
+    ///Called when a thread's closure returns or panics
+    pub (crate) fn finished_closure(&self) {
+        self._running.fetch_sub(1, Ordering::Release);
+    }
+
+    ///Returns true if no thread can create any more work
+    pub (crate) fn idle(&self) -> bool {
+        self._pending.load(Ordering::Acquire) == 0 && self._running.load(Ordering::Acquire) == 0
+    }
+}
+
+///Marks a thread's closure as finished when dropped, even if the closure panicked
+pub (crate) struct Running<'a, D, M>(pub (crate) &'a TaskPool<D, M>);
+
+impl<'a, D, M> Drop for Running<'a, D, M> {
+    fn drop(&mut self) {
+        self.0.finished_closure();
+    }
+}
+
+///Wait a little before looking for work again, yielding at first then sleeping for longer and longer up to `max`
+pub (crate) fn backoff(attempts: u32, max: Duration) {
+    if attempts < 32 {
+        std::thread::yield_now();
+    } else {
+        std::thread::sleep(Duration::from_micros(50 << (attempts - 32).min(10)).min(max));
+    }
+}
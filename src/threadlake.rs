@@ -1,10 +1,25 @@
 use std::thread::{JoinHandle, Builder};
-use crate::threadutilities::{ThreadUtilities, Signal};
+use crate::threadutilities::{ThreadUtilities, Signal, PerThread};
 use std::sync::{Mutex, Arc};
 use std::sync::mpsc::{Receiver, Sender, channel};
 use crate::iterators::JoinedIterator;
 use crate::iterators::ThreadIterator;
 use std::ops::Deref;
+use crate::channels::{Channels, Senders, Receivers, ChannelReceiver, Select};
+use crate::registry::Registry;
+use crate::watch::Watch;
+use crate::incumbent::Incumbent;
+use crate::frontier::Frontier;
+use crate::traits::Score;
+use crate::queue::{Queue, Close};
+use crate::router::{Router, Departing};
+use std::hash::Hash;
+use std::time::Duration;
+use crate::schedule::Cursors;
+use crate::tasks::{TaskPool, Running};
+use crate::barrier::{Barrier, Leaving, POISONED};
+use std::panic::resume_unwind;
+use std::mem;
 
 ///A high level thread pool
 ///
@@ -17,15 +32,21 @@ pub struct ThreadLake<D, R, M = ()> {
     _sender: Sender<M>,
     _data: Arc<D>,
     _names: Vec<String>,
+    _channels: Receivers,
+    _registry: Arc<Registry>,
+    _check_sleep: Duration,
+    _cursors: Arc<Cursors>,
 }
 
 impl<M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static> ThreadLake<D, R, M> {
 
     ///Create a thread lake with the number of threads as a closure that provides the available concurrency, and the data to send
-    pub (crate) fn with_data(_max_threads: usize, data: D, names: Vec<String>) -> Self
+    pub (crate) fn with_data(_max_threads: usize, data: D, names: Vec<String>, channels: Channels, registry: Registry, check_sleep: Duration) -> Self
     {
         let (_sender, _receiver) = channel();
 
+        let channels = Arc::new(channels);
+
         Self {
             _max_threads,
             _handles: Vec::with_capacity(_max_threads),
@@ -34,6 +55,10 @@ impl<M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static> ThreadLake<
             _sender,
             _data: Arc::new(data),
             _names: names,
+            _channels: Receivers(channels),
+            _registry: Arc::new(registry),
+            _check_sleep: check_sleep,
+            _cursors: Arc::new(Cursors::new(_max_threads)),
         }
     }
 
@@ -43,27 +68,61 @@ impl<M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static> ThreadLake<
     {
         let rcf = Arc::new(f);
 
+        //Threads hold the only references to the senders, so the channels close when the last thread finishes
+        let senders = Arc::new(Senders(self._channels.0.clone()));
+
+        let tasks = Arc::new(TaskPool::new(self._max_threads));
+
+        let barrier = Arc::new(Barrier::new(self._max_threads));
+
         for id in 0..self._max_threads {
 
             let c = rcf.clone();
 
-            let utility = ThreadUtilities {
-                _index: id,
-                _max_count: self._max_threads,
-                _name: if self._names.is_empty() { format!("ThreadLake thread {}", id) } else { self._names[id].clone() },
-                _check: self._signal.clone(),
-                _message: self._sender.clone(),
-                _arc: self._data.clone(),
-            };
+            let local = Arc::new(PerThread::new());
+
+            let utility = self.utility(id, &senders, &tasks, &barrier, &local);
+
+            //A second set of utilities, used to keep helping with forked and spawned tasks once the closure has returned
+            let helper = self.utility(id, &senders, &tasks, &barrier, &local);
 
             let builder = Builder::new().name(utility._name.clone());
 
-            self._handles.push(builder.spawn(move || c(utility)).unwrap());
+            self._handles.push(builder.spawn(move || {
+                let result = {
+                    let _running = Running(&helper._tasks);
+                    let _leaving = Leaving(&helper._barrier);
+                    let _departing = Departing(&helper._registry, id);
+                    c(utility)
+                };
+
+                helper.help_until_idle();
+
+                result
+            }).unwrap());
 
         }
 
     }
 
+    fn utility(&self, id: usize, senders: &Arc<Senders>, tasks: &Arc<TaskPool<D, M>>, barrier: &Arc<Barrier>, local: &Arc<PerThread>) -> ThreadUtilities<D, M> {
+        ThreadUtilities {
+            _index: id,
+            _max_count: self._max_threads,
+            _name: if self._names.is_empty() { format!("ThreadLake thread {}", id) } else { self._names[id].clone() },
+            _check: self._signal.clone(),
+            _message: self._sender.clone(),
+            _arc: self._data.clone(),
+            _channels: senders.clone(),
+            _registry: self._registry.clone(),
+            _check_sleep: self._check_sleep,
+            _cursors: self._cursors.clone(),
+            _local: local.clone(),
+            _tasks: tasks.clone(),
+            _barrier: barrier.clone(),
+        }
+    }
+
     /// Continue execution for all threads
     ///
     /// This will only work for the threads that occasionally call [`ThreadUtilities::check`]
@@ -85,6 +144,76 @@ impl<M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static> ThreadLake<
         *self._signal.lock().unwrap() = Signal::Pause;
     }
 
+    ///Replace the value in the watch slot declared for `W` with [`crate::builder::Builder::watch`]
+    ///
+    /// Threads pick up the new value the next time they call [`ThreadUtilities::current`]
+    pub fn publish<W: Send + Sync + 'static>(&self, value: W) {
+        self._registry.get::<Watch<W>>().expect("no watch slot was declared for this type").publish(value);
+    }
+
+    ///Get the incumbent declared with [`crate::builder::Builder::incumbent`]
+    ///
+    /// Hold on to it across [`ThreadLake::join`] and call [`Incumbent::take`] to get the winning solution
+    pub fn incumbent<S: Score, V: Send + 'static>(&self) -> Arc<Incumbent<S, V>> {
+        self._registry.get_arc().expect("no incumbent was declared for these types")
+    }
+
+    ///Get the frontier declared with [`crate::builder::Builder::frontier`]
+    ///
+    /// Hold on to it across [`ThreadLake::join`] to read the visited set once the threads are done
+    pub fn frontier<T: Send + Sync + 'static>(&self) -> Arc<Frontier<T>> {
+        self._registry.get_arc().expect("no frontier was declared for this type")
+    }
+
+    ///Add an item to the work queue declared for `T` with [`crate::builder::Builder::queue`]
+    ///
+    /// Blocks while a bounded queue is full. Fails, giving the item back, once the queue has been closed
+    pub fn submit<T: Send + 'static>(&self, item: T) -> Result<(), T> {
+        self.queue::<T>().push(item)
+    }
+
+    ///Add every item from an iterator to the work queue for `T`, stopping at the first item that cannot be added
+    pub fn submit_all<T: Send + 'static, I: IntoIterator<Item = T>>(&self, items: I) -> Result<(), T> {
+        items.into_iter().try_for_each(|item| self.submit(item))
+    }
+
+    ///Close the work queue for `T`. Threads finish taking the items already submitted, then [`ThreadUtilities::next_item`] returns `None`
+    ///
+    /// Queues are closed automatically when the lake is joined or dropped
+    pub fn close_queue<T: Send + 'static>(&self) {
+        self.queue::<T>().close();
+    }
+
+    ///Send a message to the thread that owns `key`, through the router declared for `T` with [`crate::builder::Builder::router`]
+    ///
+    /// Fails, giving the message back, once the router has been closed
+    pub fn route<K: Hash + ?Sized, T: Send + 'static>(&self, key: &K, message: T) -> Result<(), T> {
+        let router = self.router::<T>();
+
+        if router.closed() {
+            return Err(message)
+        }
+
+        router.send(key, message);
+        Ok(())
+    }
+
+    ///Close the router for `T`. Threads handle every message already routed, including any they route to each other,
+    /// then [`ThreadUtilities::next_routed`] returns `None`
+    ///
+    /// Routers are closed automatically when the lake is joined or dropped
+    pub fn close_router<T: Send + 'static>(&self) {
+        self.router::<T>().close();
+    }
+
+    fn router<T: Send + 'static>(&self) -> &Router<T> {
+        self._registry.get().expect("no router was declared for this type")
+    }
+
+    fn queue<T: Send + 'static>(&self) -> &Queue<T> {
+        self._registry.get().expect("no work queue was declared for this type")
+    }
+
     ///Iterates over [`JoinedIterator`] and consumes the results
     ///
     /// Moves the data out of the lake, if there are no other references to it
@@ -96,13 +225,51 @@ impl<M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static> ThreadLake<
         Arc::try_unwrap(data).ok()
     }
 
+    ///Join every thread and return their results, passing on the panic if any thread panicked
+    ///
+    /// A panic that poisoned a barrier is passed on in preference to the panics it caused in the threads waiting there
+    pub (crate) fn join_all(self) -> Vec<R> {
+        let mut results = Vec::with_capacity(self._max_threads);
+        let mut panic = None;
+
+        for result in self.join_iter() {
+            match result {
+                Ok(result) => results.push(result),
+                Err(payload) => {
+                    let poisoned = payload.downcast_ref::<String>().is_some_and(|message| message == POISONED);
+
+                    match &panic {
+                        None => panic = Some((payload, poisoned)),
+                        Some((_, true)) if !poisoned => panic = Some((payload, poisoned)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if let Some((payload, _)) = panic {
+            resume_unwind(payload);
+        }
+
+        results
+    }
+
+    ///Like [`ThreadLake::join_all`], but also moves the data out of the lake
+    pub (crate) fn join_with_data(self) -> (Vec<R>, D) {
+        let data = self.arc();
+        let results = self.join_all();
+
+        (results, Arc::try_unwrap(data).ok().expect("the lake still holds the data"))
+    }
+
     ///An iterator over each thread, calling join and returning the result
-    pub fn join_iter(self) -> JoinedIterator<R> {
-        JoinedIterator { _it: self._handles.into_iter() }
+    pub fn join_iter(mut self) -> JoinedIterator<R> {
+        self._registry.close_queues();
+        JoinedIterator { _it: mem::take(&mut self._handles).into_iter() }
     }
 
     /// An iterator over each thread id and thread name pair
-    pub fn thread_iter(&self) -> ThreadIterator<R> {
+    pub fn thread_iter(&self) -> ThreadIterator<'_, R> {
         ThreadIterator { _it: self._handles.iter() }
     }
 
@@ -131,4 +298,22 @@ impl<M: Send + 'static, D: Sync + Send + 'static, R: Send + 'static> ThreadLake<
     pub fn receiver(&self) -> &Receiver<M> {
         &self._receiver
     }
+
+    ///Get the receiving end of the typed channel declared for `T` with [`crate::builder::Builder::channel`], if there is one
+    pub fn channel<T: Send + 'static>(&self) -> Option<ChannelReceiver<'_, T>> {
+        self._channels.0.get::<T>().map(|queue| ChannelReceiver { _queue: queue })
+    }
+
+    ///Wait on several typed channels at once, see [`Select`]
+    pub fn select(&self) -> Select<'_> {
+        Select::new(&self._channels.0)
+    }
+}
+
+impl<D, R, M> Drop for ThreadLake<D, R, M> {
+
+    ///Close the work queues and routers, so threads waiting on them finish once they are drained, even if the lake is never joined
+    fn drop(&mut self) {
+        self._registry.close_queues();
+    }
 }
@@ -3,6 +3,25 @@ use std::sync::mpsc::{Sender, SendError};
 use std::sync::{Arc, Mutex};
 use std::ops::Deref;
 use crate::split::{SubSlice, SubSliceMut};
+use crate::channels::Senders;
+use crate::registry::Registry;
+use crate::watch::Watch;
+use crate::incumbent::Incumbent;
+use crate::frontier::Frontier;
+use crate::rng::{Rng, Seed};
+use crate::traits::{Score, Integer, SplitRange};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::queue::{Queue, Pop};
+use crate::router::Router;
+use std::hash::Hash;
+use crate::schedule::{Cursors, Schedule, Kind};
+use crate::tasks::{TaskPool, StackJob, heap_job, backoff};
+use crate::barrier::Barrier;
+use crate::disjointer::Disjointer;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[derive(Clone)]
 pub (crate) enum Signal {
@@ -12,6 +31,22 @@ pub (crate) enum Signal {
     Stop,
 }
 
+///State that belongs to one thread of a lake
+///
+/// Shared between the utilities a thread's closure is given and the ones it keeps helping with tasks with once the
+/// closure has returned, so both see the same watch versions and schedule count.
+pub (crate) struct PerThread {
+    _seen: Mutex<HashMap<TypeId, u64>>, //Version of each watch slot this thread last read
+    _schedules: AtomicUsize, //Number of dynamic or guided schedules this thread has started
+}
+
+impl PerThread {
+
+    pub (crate) fn new() -> Self {
+        Self { _seen: Mutex::new(HashMap::new()), _schedules: AtomicUsize::new(0) }
+    }
+}
+
 ///An object sent to each thread that contains useful information and functions
 pub struct ThreadUtilities<D, M = ()> {
     pub (crate) _index: usize,
@@ -20,6 +55,13 @@ pub struct ThreadUtilities<D, M = ()> {
     pub (crate) _check: Arc<Mutex<Signal>>,
     pub (crate) _message: Sender<M>,
     pub (crate) _arc: Arc<D>,
+    pub (crate) _channels: Arc<Senders>,
+    pub (crate) _registry: Arc<Registry>,
+    pub (crate) _check_sleep: Duration,
+    pub (crate) _cursors: Arc<Cursors>,
+    pub (crate) _local: Arc<PerThread>,
+    pub (crate) _tasks: Arc<TaskPool<D, M>>,
+    pub (crate) _barrier: Arc<Barrier>,
 }
 
 impl<D, M> ThreadUtilities<D, M> {
@@ -38,15 +80,17 @@ impl<D, M> ThreadUtilities<D, M> {
     /// return true if a stop signal is detected
     pub fn check(&self) -> bool {
 
-        let signal = {
-            self._check.lock().unwrap().deref().clone()
-        };
+        loop {
+            let signal = {
+                self._check.lock().unwrap().deref().clone()
+            };
 
-        match signal {
-            Signal::None => {false}
-            Signal::Play => {false}
-            Signal::Pause => {todo!()}
-            Signal::Stop => {true}
+            match signal {
+                Signal::None => {return false}
+                Signal::Play => {return false}
+                Signal::Pause => {std::thread::sleep(self._check_sleep)}
+                Signal::Stop => {return true}
+            }
         }
 
     }
@@ -56,6 +100,132 @@ impl<D, M> ThreadUtilities<D, M> {
         self._message.send(message)
     }
 
+    ///Send a message on the typed channel declared for `T` with [`crate::builder::Builder::channel`]
+    ///
+    /// Fails if no channel was declared for `T`, or if the lake is no longer receiving
+    pub fn post<T: Send + 'static>(&self, message: T) -> Result<(), SendError<T>> {
+        match self._channels.0.get::<T>() {
+            Some(queue) => queue.push(message).map_err(SendError),
+            None => Err(SendError(message)),
+        }
+    }
+
+    ///Get the latest value in the watch slot declared for `W` with [`crate::builder::Builder::watch`]
+    ///
+    /// The snapshot is unaffected by later calls to [`crate::threadlake::ThreadLake::publish`]
+    pub fn current<W: Send + Sync + 'static>(&self) -> Arc<W> {
+        let (value, version) = self.watch::<W>().current();
+        self._local._seen.lock().unwrap().insert(TypeId::of::<W>(), version);
+        value
+    }
+
+    ///Returns true if the watch slot for `W` has been published to since this thread last called [`ThreadUtilities::current`]
+    pub fn changed<W: Send + Sync + 'static>(&self) -> bool {
+        let seen = self._local._seen.lock().unwrap().get(&TypeId::of::<W>()).copied().unwrap_or(0);
+        self.watch::<W>().version() != seen
+    }
+
+    fn watch<W: Send + Sync + 'static>(&self) -> &Watch<W> {
+        self._registry.get::<Watch<W>>().expect("no watch slot was declared for this type")
+    }
+
+    ///Get the incumbent declared with [`crate::builder::Builder::incumbent`], shared by all threads
+    pub fn incumbent<S: Score, V: Send + 'static>(&self) -> &Incumbent<S, V> {
+        self._registry.get().expect("no incumbent was declared for these types")
+    }
+
+    ///A random number stream for the logical block of work numbered `block`, derived from the seed set with [`crate::builder::Builder::seed`]
+    ///
+    /// The stream depends on the seed and the block, but not on the thread asking for it, so splitting work into blocks
+    /// by something other than the thread index, such as with [`ThreadUtilities::range`] over a fixed number of blocks,
+    /// gives the same results at any thread count
+    pub fn rng(&self, block: u64) -> Rng {
+        Rng::new(self._registry.get::<Seed>().map_or(0, |seed| seed.0), block)
+    }
+
+    ///Get the frontier declared with [`crate::builder::Builder::frontier`], shared by all threads
+    pub fn frontier<T: Send + Sync + 'static>(&self) -> &Frontier<T> {
+        self._registry.get().expect("no frontier was declared for this type")
+    }
+
+    ///Take the next item from the work queue declared for `T` with [`crate::builder::Builder::queue`], waiting for one if the queue is empty
+    ///
+    /// Returns `None` once the queue is closed and drained, or if the lake is stopped. Waiting threads also honour the pause signal, as with [`ThreadUtilities::check`]
+    pub fn next_item<T: Send + 'static>(&self) -> Option<T> {
+        let queue = self._registry.get::<Queue<T>>().expect("no work queue was declared for this type");
+
+        loop {
+            if self.check() {
+                return None
+            }
+
+            match queue.pop_timeout(self._check_sleep) {
+                Pop::Item(item) => return Some(item),
+                Pop::Closed => return None,
+                Pop::Empty => {}
+            }
+        }
+    }
+
+    ///Send a message to the thread that owns `key`, through the router declared for `T` with [`crate::builder::Builder::router`]
+    ///
+    /// Threads can route messages while handling others, even after the lake has closed the router
+    pub fn route<K: Hash + ?Sized, T: Send + 'static>(&self, key: &K, message: T) {
+        self.router::<T>().send(key, message);
+    }
+
+    ///Send a message to a thread picked with [`ThreadUtilities::owner`], for messages that hold their own key
+    pub (crate) fn route_to<T: Send + 'static>(&self, owner: usize, message: T) {
+        self.router::<T>().send_to(owner, message);
+    }
+
+    ///The index of the thread that owns `key` in the router for `T`
+    pub fn owner<K: Hash + ?Sized, T: Send + 'static>(&self, key: &K) -> usize {
+        self.router::<T>().owner(key)
+    }
+
+    ///Take the next message routed to this thread, waiting for one if the inbox is empty
+    ///
+    /// Taking a message marks the previous one as handled. Returns `None` once the router is closed and every thread
+    /// has handled all of its messages, or if the lake is stopped. Every thread taking part should keep calling this
+    /// until it returns `None`, since the others wait for its last message to be handled. A thread whose closure returns
+    /// early is taken out of the router, and any messages routed to it from then on are dropped.
+    pub fn next_routed<T: Send + 'static>(&self) -> Option<T> {
+        let router = self.router::<T>();
+
+        router.release(self._index);
+
+        loop {
+            if self.check() {
+                return None
+            }
+
+            if let Some(message) = router.try_take(self._index) {
+                return Some(message)
+            }
+
+            if router.finished() {
+                return None
+            }
+
+            if let Some(message) = router.take_timeout(self._index, self._check_sleep) {
+                return Some(message)
+            }
+        }
+    }
+
+    ///Take a message routed to this thread if one is waiting, marking the previous one as handled
+    pub fn try_routed<T: Send + 'static>(&self) -> Option<T> {
+        let router = self.router::<T>();
+
+        router.release(self._index);
+        router.try_take(self._index)
+    }
+
+    fn router<T: Send + 'static>(&self) -> &Router<T> {
+        self._registry.get().expect("no router was declared for this type")
+    }
+
     ///Can split a range from 0..total into roughly equal sized ranges, based on the thread index.
     ///
     /// Can be used to divide a list into disjoint sublists for processing
@@ -69,9 +239,100 @@ impl<D, M> ThreadUtilities<D, M> {
         }
     }
 
+    ///Like [`ThreadUtilities::range`], but the remainder is spread over the first threads, one element each
+    ///
+    /// No thread gets more than one element more than any other, where [`ThreadUtilities::range`] gives the whole
+    /// remainder to the last thread
+    pub fn balanced_range(&self, total: usize) -> std::ops::Range<usize> {
+        let width = total / self._max_count;
+        let remainder = total % self._max_count;
+
+        let start = self._index * width + self._index.min(remainder);
+        let end = start + width + usize::from(self._index < remainder);
+
+        start..end
+    }
+
+    ///Split any range of integers between the threads, returning this thread's piece, or `None` if it gets no values
+    ///
+    /// Works for [`std::ops::Range`] and [`std::ops::RangeInclusive`] of every primitive integer type, including
+    /// ranges with negative starts and ranges that span the whole type, such as `0..=u64::MAX`. As with
+    /// [`ThreadUtilities::balanced_range`], the remainder is spread over the first threads
+    pub fn split_range<R: SplitRange>(&self, range: R) -> Option<std::ops::RangeInclusive<R::Item>> {
+        let (start, last) = range.bounds()?;
+        let (first, end) = self.balanced_piece(last)?;
+
+        Some(start.advance(first)..=start.advance(end))
+    }
+
+    ///Like [`ThreadUtilities::split_range`], but only every `step`-th value from the start of the range is included
+    ///
+    /// The values are shared out between the threads, rather than the range, so each thread gets as many as any other,
+    /// give or take one. Panics if `step` is zero
+    pub fn split_range_step<R>(&self, range: R, step: usize) -> Option<std::iter::StepBy<std::ops::RangeInclusive<R::Item>>>
+        where R: SplitRange, std::ops::RangeInclusive<R::Item>: Iterator<Item = R::Item>
+    {
+        assert!(step > 0, "the step must be at least one");
+
+        let (start, last) = range.bounds()?;
+        let step = step as u128;
+        let (first, end) = self.balanced_piece(last / step)?;
+
+        Some((start.advance(first * step)..=start.advance(end * step)).step_by(step as usize))
+    }
+
+    ///The first and last of the indices `0..=last` given to this thread by a balanced split, or `None` if it gets none
+    ///
+    /// Takes the last index rather than the count, since a count of every `u128` would not fit in one
+    fn balanced_piece(&self, last: u128) -> Option<(u128, u128)> {
+        let threads = self._max_count as u128;
+        let index = self._index as u128;
+
+        //The count is last + 1, so divide that without forming it
+        let width = last / threads + (last % threads + 1) / threads;
+        let remainder = (last % threads + 1) % threads;
+
+        let first = index * width + index.min(remainder);
+        let len = width + u128::from(index < remainder);
+
+        (len > 0).then(|| (first, first + (len - 1)))
+    }
+
+    ///Split 0..total into chunks of size `chunk`, which threads claim one at a time as they finish the last
+    ///
+    /// Unlike [`ThreadUtilities::range`], threads that get through their work quickly go on to claim more of it, which
+    /// suits loops where some elements take much longer than others. Every thread must start the same dynamic and guided
+    /// schedules in the same order, as the n-th schedule started by each thread claims chunks from the same shared cursor
+    pub fn dynamic_range(&self, total: usize, chunk: usize) -> Schedule {
+        assert!(chunk > 0, "chunks must hold at least one element");
+        self.schedule(total, Kind::Dynamic(chunk))
+    }
+
+    ///Like [`ThreadUtilities::dynamic_range`], but chunks start large and shrink as the work runs out, down to `min_chunk`
+    ///
+    /// Each chunk is the remaining work divided by the number of threads, which keeps the number of claims low while still
+    /// balancing the end of the loop
+    pub fn guided_range(&self, total: usize, min_chunk: usize) -> Schedule {
+        assert!(min_chunk > 0, "chunks must hold at least one element");
+        self.schedule(total, Kind::Guided(min_chunk))
+    }
+
+    fn schedule(&self, total: usize, kind: Kind) -> Schedule {
+        let n = self._local._schedules.fetch_add(1, Ordering::Relaxed);
+
+        Schedule { _cursor: self._cursors.get(n), _total: total, _threads: self._max_count, _kind: kind }
+    }
+
     ///Similar to [`ThreadUtilities::range`], splits a slice into disjoint slices based on the thread index
     pub fn split_slice<'s, S>(&self, slice: & 's [S]) -> SubSlice<'s, S> {
-        SubSlice { _slice: &slice[self.range(slice.len())], _width: slice.len() / self._max_count }
+        let range = self.range(slice.len());
+        SubSlice { _offset: range.start, _slice: &slice[range], _width: slice.len() / self._max_count }
+    }
+
+    ///Like [`ThreadUtilities::split_slice`], but split with [`ThreadUtilities::balanced_range`]
+    pub fn balanced_split_slice<'s, S>(&self, slice: & 's [S]) -> SubSlice<'s, S> {
+        let range = self.balanced_range(slice.len());
+        SubSlice { _offset: range.start, _width: range.len(), _slice: &slice[range] }
     }
 
     ///Similar to [`ThreadUtilities::split_slice`], but for mutable slices.
@@ -79,7 +340,219 @@ impl<D, M> ThreadUtilities<D, M> {
     /// Should only be used by the [`Disjointer`]
     pub (crate) fn split_slice_mut<'s, S>(&self, slice: & 's mut [S]) -> SubSliceMut<'s, S> {
         let len = slice.len();
-        SubSliceMut { _slice: &mut slice[self.range(len)], _width: len / self._max_count}
+        let range = self.range(len);
+        SubSliceMut { _offset: range.start, _slice: &mut slice[range], _width: len / self._max_count}
+    }
+
+    ///Similar to [`ThreadUtilities::balanced_split_slice`], but for mutable slices.
+    ///
+    /// Should only be used by the [`Disjointer`]
+    pub (crate) fn balanced_split_slice_mut<'s, S>(&self, slice: & 's mut [S]) -> SubSliceMut<'s, S> {
+        let range = self.balanced_range(slice.len());
+        SubSliceMut { _offset: range.start, _width: range.len(), _slice: &mut slice[range] }
+    }
+
+    ///Wait until every thread in the lake has reached the barrier. Returns true for exactly one of the threads each time
+    ///
+    /// Threads whose closure has already returned are not waited for. If a thread panics, the threads waiting at the
+    /// barrier panic too, instead of waiting forever.
+    pub fn barrier(&self) -> bool {
+        self._barrier.wait()
+    }
+
+    ///Give a value to every thread in the lake, and get back the values given by each of them, in thread index order
+    ///
+    /// Every thread must call this the same number of times, as it waits at the barrier twice
+    pub fn all_gather<V: Clone + Send + 'static>(&self, value: V) -> Vec<V> {
+        self._barrier.put(self._index, value);
+        self.barrier();
+
+        let values = self._barrier.gather();
+
+        //Nobody can replace their value until everyone has read them all
+        self.barrier();
+
+        values
+    }
+
+    ///Combine a value from every thread in the lake with `op`, in a tree, returning the result to the thread with index 0
+    ///
+    /// Values are combined in thread index order, so `op` needs to be associative but not commutative. The other threads
+    /// get `None`. As with [`ThreadUtilities::all_gather`], every thread must take part.
+    pub fn reduce<V, F>(&self, value: V, op: F) -> Option<V>
+        where V: Send + 'static, F: Fn(V, V) -> V
+    {
+        let mut value = Some(value);
+        let mut step = 1;
+
+        while step < self._max_count {
+            //Threads hand their value to the left and drop out, until only the first thread is left
+            if self._index % (2 * step) == step {
+                self._barrier.put(self._index, value.take().unwrap());
+            }
+
+            self.barrier();
+
+            if self._index.is_multiple_of(2 * step) && self._index + step < self._max_count {
+                let right = self._barrier.take(self._index + step);
+                value = Some(op(value.take().unwrap(), right));
+            }
+
+            step *= 2;
+        }
+
+        //Nobody can leave a value for the next exchange until the last one has been taken
+        self.barrier();
+
+        value
+    }
+
+    ///Replace every element of `data` with `op` applied to it and every element before it, in place
+    ///
+    /// Each thread scans its own piece, the threads exchange the totals of their pieces, then each thread combines the
+    /// total of every piece before its own into each of its elements. `op` must be associative, but it need not be
+    /// commutative. As with [`ThreadUtilities::all_gather`], every thread must take part, and no other piece of `data`
+    /// may be in use at the same time.
+    pub fn scan_inclusive<T, F>(&self, data: &Disjointer<T>, op: F)
+        where T: Clone + Send + 'static, F: Fn(&T, &T) -> T
+    {
+        self.scan(data, None, op)
+    }
+
+    ///Like [`ThreadUtilities::scan_inclusive`], but every element is replaced with `op` applied to every element before it,
+    /// starting from `identity`, which must leave any value unchanged
+    pub fn scan_exclusive<T, F>(&self, data: &Disjointer<T>, identity: T, op: F)
+        where T: Clone + Send + 'static, F: Fn(&T, &T) -> T
+    {
+        self.scan(data, Some(identity), op)
+    }
+
+    fn scan<T, F>(&self, data: &Disjointer<T>, identity: Option<T>, op: F)
+        where T: Clone + Send + 'static, F: Fn(&T, &T) -> T
+    {
+        let mut piece = data.piece(self);
+        let len = piece._slice.len();
+
+        //A thread with an empty piece has no total to pass on
+        let total = match identity {
+            None => {
+                for index in 1..len {
+                    piece[index] = op(&piece[index - 1], &piece[index]);
+                }
+
+                piece._slice.last().cloned()
+            }
+            Some(identity) => {
+                let mut running = identity;
+
+                for element in piece.iter_mut() {
+                    let next = op(&running, element);
+                    *element = std::mem::replace(&mut running, next);
+                }
+
+                (len > 0).then_some(running)
+            }
+        };
+
+        let totals = self.all_gather(total);
+
+        if let Some(offset) = totals[..self._index].iter().flatten().cloned().reduce(|a, b| op(&a, &b)) {
+            for element in piece.iter_mut() {
+                *element = op(&offset, element);
+            }
+        }
+    }
+
+    ///Run `a` and `b` in parallel, and return both results
+    ///
+    /// While this thread runs `a`, `b` waits in this thread's deque where an idle thread in the lake can steal it. If
+    /// nobody has by the time `a` is finished, this thread runs `b` itself. Since either closure can call `join` again,
+    /// this suits recursive divide and conquer, and uneven forks still keep every thread in the lake busy.
+    pub fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+        where A: FnOnce(&Self) -> RA, B: FnOnce(&Self) -> RB + Send, RB: Send
+    {
+        let job = StackJob::new(b, self._index);
+
+        self._tasks.push(self._index, job.as_job_ref());
+
+        let result_a = catch_unwind(AssertUnwindSafe(|| a(self)));
+
+        //Whatever happened to `a`, `b` must be finished before its stack frame goes away
+        let mut attempts = 0;
+        while !job.done() {
+            match self._tasks.find(self._index) {
+                Some(other) => {
+                    unsafe { other.execute(self) };
+                    attempts = 0;
+                }
+                None if attempts < 32 => {
+                    std::thread::yield_now();
+                    attempts += 1;
+                }
+                //Whoever stole `b` wakes us as soon as it is done
+                None => self._tasks.sleep(self._index, || job.done(), self._check_sleep),
+            }
+        }
+
+        let result_b = job.into_result();
+
+        match (result_a, result_b) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(payload), _) | (_, Err(payload)) => resume_unwind(payload),
+        }
+    }
+
+    ///Spawn a task onto this thread's deque, to be run by whichever thread in the lake gets to it first
+    ///
+    /// Threads keep running spawned tasks after their closure returns, so every task has finished by the time the
+    /// lake is joined. A task that panics makes one of the threads report the panic when it is joined. Tasks honour
+    /// [`ThreadUtilities::check`]: they wait while the lake is paused, and once it is stopped, tasks that have not
+    /// started yet are dropped without running
+    pub fn spawn_task<F>(&self, task: F)
+        where F: FnOnce(&ThreadUtilities<D, M>) + Send + 'static
+    {
+        self._tasks._pending.fetch_add(1, Ordering::AcqRel);
+        self._tasks.push(self._index, heap_job(task));
+    }
+
+    ///Help run tasks until every task spawned with [`ThreadUtilities::spawn_task`], by any thread, has finished
+    pub fn run_tasks(&self) {
+        self.help(|| self._tasks._pending.load(Ordering::Acquire) == 0);
+    }
+
+    ///Run forked and spawned work once this thread's closure has returned, until no thread can create more of it
+    ///
+    /// Returns straight away if nothing has been forked or spawned yet. Any thread that forks or spawns later helps
+    /// with its own work once its closure returns, so the work still gets done without this thread
+    pub (crate) fn help_until_idle(&self) {
+        if !self._tasks.used() {
+            return
+        }
+
+        self.help(|| self._tasks.idle());
+
+        if let Some(payload) = self._tasks._panics.lock().unwrap().pop() {
+            resume_unwind(payload)
+        }
+    }
+
+    ///Run tasks until `finished` returns true, checking it whenever there are none to run
+    pub (crate) fn help<F: Fn() -> bool>(&self, finished: F) {
+        let mut attempts = 0;
+
+        loop {
+            match self._tasks.find(self._index) {
+                Some(job) => {
+                    unsafe { job.execute(self) };
+                    attempts = 0;
+                }
+                None if finished() => return,
+                None => {
+                    backoff(attempts, self._check_sleep);
+                    attempts += 1;
+                }
+            }
+        }
     }
 
     ///Get the underlying data
@@ -1,5 +1,6 @@
 
 use std::io::Result;
+use std::ops::{Add, Sub, Range, RangeInclusive};
 
 ///ThreadCount describes types that take the available concurrency (in the form of Option<usize) and calculate from this the number of threads to spawn
 pub trait ThreadCount {
@@ -20,6 +21,13 @@ impl<F> ThreadCount for F
     }
 }
 
+///The number of threads to run work over `len` elements on, never more than there are elements
+///
+/// Always at least one, so a thread count that comes out as zero still does the work instead of skipping it
+pub (crate) fn threads_for<C: ThreadCount>(thread_count: C, len: usize) -> usize {
+    thread_count.get(std::thread::available_parallelism().map(|x| x.get())).min(len).max(1)
+}
+
 ///Used solely as a [`ThreadCount`] that makes use of the full available parallelism
 pub struct FullParallelism;
 
@@ -56,3 +64,156 @@ impl<F> ThreadName for F
         (self)(index)
     }
 }
+
+///Score describes values that can be compared without taking a lock, by mapping them onto a `u64` that sorts the same way
+pub trait Score: Copy + PartialOrd + Send + Sync + 'static {
+    fn to_key(self) -> u64;
+    fn from_key(key: u64) -> Self;
+}
+
+impl Score for u64 {
+    fn to_key(self) -> u64 {
+        self
+    }
+
+    fn from_key(key: u64) -> Self {
+        key
+    }
+}
+
+impl Score for usize {
+    fn to_key(self) -> u64 {
+        self as u64
+    }
+
+    fn from_key(key: u64) -> Self {
+        key as usize
+    }
+}
+
+impl Score for u32 {
+    fn to_key(self) -> u64 {
+        self as u64
+    }
+
+    fn from_key(key: u64) -> Self {
+        key as u32
+    }
+}
+
+impl Score for i64 {
+    fn to_key(self) -> u64 {
+        (self as u64) ^ (1 << 63)
+    }
+
+    fn from_key(key: u64) -> Self {
+        (key ^ (1 << 63)) as i64
+    }
+}
+
+impl Score for i32 {
+    fn to_key(self) -> u64 {
+        (self as i64).to_key()
+    }
+
+    fn from_key(key: u64) -> Self {
+        i64::from_key(key) as i32
+    }
+}
+
+impl Score for f64 {
+    fn to_key(self) -> u64 {
+        let bits = self.to_bits();
+        //Negative numbers sort backwards, so flip all their bits. Positive numbers just need to sort above them
+        if bits >> 63 == 1 { !bits } else { bits | (1 << 63) }
+    }
+
+    fn from_key(key: u64) -> Self {
+        f64::from_bits(if key >> 63 == 1 { key & !(1 << 63) } else { !key })
+    }
+}
+
+impl Score for f32 {
+    fn to_key(self) -> u64 {
+        (self as f64).to_key()
+    }
+
+    fn from_key(key: u64) -> Self {
+        f64::from_key(key) as f32
+    }
+}
+
+///Float describes the floating point types that can be summed with [`crate::deterministic::lake_sum_deterministic`]
+pub trait Float: Copy + PartialOrd + Send + Sync + 'static + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
+    fn abs(self) -> Self;
+}
+
+impl Float for f64 {
+    const ZERO: Self = 0.0;
+
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+}
+
+impl Float for f32 {
+    const ZERO: Self = 0.0;
+
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+}
+
+///Integer describes the types whose ranges can be split with [`crate::threadutilities::ThreadUtilities::split_range`]
+///
+/// Distances are measured as `u128`, which holds the distance between any two values of any of these types
+pub trait Integer: Copy + PartialOrd + Send + Sync + 'static {
+    ///How far `end` is above `start`, which must not be above it
+    fn distance(start: Self, end: Self) -> u128;
+    ///The value `offset` above `self`, which must not go past the largest value of the type
+    fn advance(self, offset: u128) -> Self;
+}
+
+macro_rules! integer {
+    ($($t:ty => $unsigned:ty),*) => {
+        $(
+            impl Integer for $t {
+                fn distance(start: Self, end: Self) -> u128 {
+                    //The wrapped difference, read as unsigned, is the true distance even when it overflows the signed type
+                    end.wrapping_sub(start) as $unsigned as u128
+                }
+
+                fn advance(self, offset: u128) -> Self {
+                    self.wrapping_add(offset as $t)
+                }
+            }
+        )*
+    };
+}
+
+integer!(u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize,
+         i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+
+///SplitRange describes the ranges that can be split with [`crate::threadutilities::ThreadUtilities::split_range`]
+pub trait SplitRange {
+    type Item: Integer;
+    ///The first value in the range and its distance to the last, or `None` if the range is empty
+    fn bounds(self) -> Option<(Self::Item, u128)>;
+}
+
+impl<T: Integer> SplitRange for Range<T> {
+    type Item = T;
+
+    fn bounds(self) -> Option<(T, u128)> {
+        (self.start < self.end).then(|| (self.start, T::distance(self.start, self.end) - 1))
+    }
+}
+
+impl<T: Integer> SplitRange for RangeInclusive<T> {
+    type Item = T;
+
+    fn bounds(self) -> Option<(T, u128)> {
+        (!self.is_empty()).then(|| (*self.start(), T::distance(*self.start(), *self.end())))
+    }
+}
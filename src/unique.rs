@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::builder::Builder;
+use crate::disjointer::{Disjointer, into_uninit, from_uninit};
+use crate::threadutilities::ThreadUtilities;
+use crate::traits::{ThreadCount, threads_for};
+
+///The order of the elements returned by [`lake_unique`] and [`lake_dedup_by_key`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniqueOrder {
+    ///Elements are in the order they first appear in the input
+    FirstOccurrence,
+    ///Elements are in no particular order, which saves a pass over the data
+    Unordered,
+}
+
+struct Unique<T, H, S> {
+    _input: Disjointer<MaybeUninit<T>>,
+    _output: Disjointer<MaybeUninit<T>>,
+    _kept: Vec<AtomicBool>, //Only used to keep the first occurrence order
+    _hash: H,
+    _same: S,
+    _order: UniqueOrder,
+}
+
+impl<T: Send + 'static, H, S> Unique<T, H, S> {
+
+    ///An element of the input, which must not have been moved out or dropped. Each index belongs to one thread at a time
+    unsafe fn element(&self, index: usize) -> &T {
+        self._input.slice(index..index + 1)[0].assume_init_ref()
+    }
+
+    ///Drop an element of the input, leaving its slot uninitialised
+    unsafe fn drop_element(&self, index: usize) {
+        self._input.slice_mut(index..index + 1)[0].assume_init_drop()
+    }
+
+    ///Move an element of the input into `slot`, leaving its slot in the input uninitialised
+    unsafe fn move_element(&self, index: usize, slot: &mut MaybeUninit<T>) {
+        ptr::copy_nonoverlapping(&self._input.slice_mut(index..index + 1)[0], slot, 1)
+    }
+}
+
+///Remove the elements of `data` that `same` says duplicate an earlier element, where duplicates have the same `hash`
+///
+/// Each thread reads its piece of the data, and sends the index of every element to the thread that owns its hash.
+/// Since duplicates always meet at the same owner, each owner finds the first occurrence of every element it owns
+/// without talking to the other threads, and drops the rest. The survivors are then moved into the output, either
+/// straight from each owner, or by a second pass over the pieces that keeps them in their original order.
+fn unique<C, T, H, S>(thread_count: C, data: Vec<T>, hash: H, same: S, order: UniqueOrder) -> Vec<T>
+    where C: ThreadCount, T: Send + 'static, H: Fn(&T) -> u64 + Send + Sync + 'static, S: Fn(&T, &T) -> bool + Send + Sync + 'static
+{
+    let len = data.len();
+    let threads = threads_for(thread_count, len);
+
+    if len == 0 {
+        return Vec::new()
+    }
+
+    //Every element is either moved into the output or dropped exactly once, and neither buffer drops any, so a panic leaks them
+    let unique = Unique {
+        _input: Disjointer::new(into_uninit(data)),
+        _output: Disjointer::new((0..len).map(|_| MaybeUninit::uninit()).collect::<Vec<_>>()),
+        _kept: (0..if order == UniqueOrder::FirstOccurrence { len } else { 0 }).map(|_| AtomicBool::new(false)).collect(),
+        _hash: hash,
+        _same: same,
+        _order: order,
+    };
+
+    let lake = Builder::with_data(threads, unique)
+        .router::<(u64, usize)>()
+        .spawn(|x: ThreadUtilities<Unique<T, H, S>>| {
+            let unique = x.data();
+            let len = unique._input.len();
+            let range = x.range(len);
+
+            //Nothing is moved or dropped until every thread is past the barrier, so the whole input can be read
+            let piece = x.split_slice(unsafe { unique._input.slice(0..len) });
+
+            for (index, element) in range.clone().zip(piece) {
+                let hash = (unique._hash)(unsafe { element.assume_init_ref() });
+                x.route(&hash, (hash, index));
+            }
+
+            x.barrier();
+
+            let mut owned = Vec::new();
+            while let Some(message) = x.try_routed::<(u64, usize)>() {
+                owned.push(message);
+            }
+
+            //In index order, the first element seen with each value is its first occurrence
+            owned.sort_unstable_by_key(|(_, index)| *index);
+
+            let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+            let mut survivors = Vec::new();
+
+            for (hash, index) in owned {
+                let first = seen.entry(hash).or_default();
+
+                if first.iter().any(|first| unsafe { (unique._same)(unique.element(*first), unique.element(index)) }) {
+                    unsafe { unique.drop_element(index) };
+                } else {
+                    first.push(index);
+                    survivors.push(index);
+                }
+            }
+
+            match unique._order {
+                UniqueOrder::Unordered => {
+                    let counts = x.all_gather(survivors.len());
+                    let before: usize = counts[..x.index()].iter().sum();
+
+                    let output = unsafe { unique._output.slice_mut(before..before + survivors.len()) };
+
+                    for (slot, index) in output.iter_mut().zip(survivors) {
+                        unsafe { unique.move_element(index, slot) };
+                    }
+
+                    counts.iter().sum()
+                }
+                UniqueOrder::FirstOccurrence => {
+                    for index in survivors {
+                        unique._kept[index].store(true, Ordering::Relaxed);
+                    }
+
+                    //Every owner has to be done flagging its survivors before any are counted
+                    x.barrier();
+
+                    let count = range.clone().filter(|index| unique._kept[*index].load(Ordering::Relaxed)).count();
+                    let counts = x.all_gather(count);
+                    let before: usize = counts[..x.index()].iter().sum();
+
+                    let output = unsafe { unique._output.slice_mut(before..before + count) };
+
+                    for (slot, index) in output.iter_mut().zip(range.filter(|index| unique._kept[*index].load(Ordering::Relaxed))) {
+                        unsafe { unique.move_element(index, slot) };
+                    }
+
+                    counts.iter().sum::<usize>()
+                }
+            }
+        });
+
+    let (totals, unique) = lake.join_with_data();
+    let total = totals[0];
+
+    let mut output = unique._output.take();
+    output.truncate(total);
+
+    //The first `total` elements of the output hold the survivors
+    unsafe { from_uninit(output) }
+}
+
+fn hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+///The distinct elements of `data`, found on a lake by sending each element to the thread that owns its hash
+pub fn lake_unique<C, T>(thread_count: C, data: Vec<T>, order: UniqueOrder) -> Vec<T>
+    where C: ThreadCount, T: Hash + Eq + Send + Sync + 'static
+{
+    unique(thread_count, data, hash, |a, b| a == b, order)
+}
+
+///The elements of `data` with distinct keys, keeping the first element with each key, found on a lake
+pub fn lake_dedup_by_key<C, T, K, F>(thread_count: C, data: Vec<T>, key: F, order: UniqueOrder) -> Vec<T>
+    where C: ThreadCount, T: Send + Sync + 'static, K: Hash + Eq, F: Fn(&T) -> K + Send + Sync + 'static
+{
+    let key = Arc::new(key);
+    let same = key.clone();
+
+    unique(thread_count, data, move |element| hash(&key(element)), move |a, b| same(a) == same(b), order)
+}
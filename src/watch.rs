@@ -0,0 +1,36 @@
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+///A slot holding the latest version of a value published by the lake
+///
+/// Publishing swaps in a new [`Arc`] rather than mutating the value, so threads reading an older
+/// snapshot keep a consistent view of it until they look again.
+pub (crate) struct Watch<W> {
+    _value: RwLock<Arc<W>>,
+    _version: AtomicU64,
+}
+
+impl<W> Watch<W> {
+
+    pub (crate) fn new(initial: W) -> Self {
+        Self { _value: RwLock::new(Arc::new(initial)), _version: AtomicU64::new(0) }
+    }
+
+    ///Replace the value, bumping the version
+    pub (crate) fn publish(&self, value: W) {
+        let mut slot = self._value.write().unwrap();
+        *slot = Arc::new(value);
+        self._version.fetch_add(1, Ordering::Release);
+    }
+
+    ///The latest value, and its version
+    pub (crate) fn current(&self) -> (Arc<W>, u64) {
+        let slot = self._value.read().unwrap();
+        (slot.clone(), self._version.load(Ordering::Acquire))
+    }
+
+    ///The latest version, without touching the lock
+    pub (crate) fn version(&self) -> u64 {
+        self._version.load(Ordering::Acquire)
+    }
+}